@@ -0,0 +1,297 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::create_profile_symlink::helpers;
+use crate::managed_manifest::{self, ManagedEntry, ManagedManifest, Ownership, UnmanagedPolicy};
+
+/// What an activation changed in the GSX folder, as target paths
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ActivationDelta {
+    pub added: Vec<String>,
+    pub retargeted: Vec<String>, // Existing entries that now point at a different source
+    pub removed: Vec<String>,
+    pub unchanged: Vec<String>,
+    pub preserved: Vec<String>, // Files the manager does not own and left alone
+}
+
+#[derive(Debug, Clone)]
+pub struct DesiredLink {
+    pub source: PathBuf,
+    pub name: OsString,
+}
+
+/// Difference between the selected profile files and what is in the GSX folder right now
+#[derive(Debug, Clone, Default)]
+pub struct LinkDiff {
+    pub add: Vec<DesiredLink>,
+    pub retarget: Vec<DesiredLink>,
+    pub remove: Vec<PathBuf>,
+    pub unchanged: Vec<DesiredLink>, // Named after the existing entry
+    pub missing_sources: Vec<String>,
+    // Entries the manager does not own, see managed_manifest::UnmanagedPolicy
+    pub preserved: Vec<PathBuf>,
+    pub blocked: Vec<PathBuf>, // Unmanaged files a selected profile would replace
+    pub unmanaged_displaced: Vec<PathBuf>, // Unmanaged files that will be replaced or removed
+}
+
+impl LinkDiff {
+    pub fn has_changes(&self) -> bool {
+        !self.add.is_empty() || !self.retarget.is_empty() || !self.remove.is_empty()
+    }
+
+    // Sources that need a new link, either because they are new or because they moved
+    pub fn sources_to_link(&self) -> Vec<String> {
+        self.add
+            .iter()
+            .chain(&self.retarget)
+            .map(|link| link.source.to_string_lossy().to_string())
+            .collect()
+    }
+
+    // Existing entries that have to leave the GSX folder, including those being retargeted
+    pub fn entries_to_displace(&self, target_dir: &Path) -> Vec<PathBuf> {
+        self.remove
+            .iter()
+            .cloned()
+            .chain(self.retarget.iter().map(|link| target_dir.join(&link.name)))
+            .collect()
+    }
+
+    pub fn delta(&self, target_dir: &Path) -> ActivationDelta {
+        let to_strings = |links: &[DesiredLink]| {
+            links
+                .iter()
+                .map(|link| target_dir.join(&link.name).to_string_lossy().to_string())
+                .collect()
+        };
+        let paths_to_strings = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect()
+        };
+
+        ActivationDelta {
+            added: to_strings(&self.add),
+            retargeted: to_strings(&self.retarget),
+            removed: paths_to_strings(&self.remove),
+            unchanged: to_strings(&self.unchanged),
+            preserved: paths_to_strings(&self.preserved),
+        }
+    }
+
+    /// The manifest describing the GSX folder once this diff is applied
+    pub fn managed_manifest(&self) -> ManagedManifest {
+        let entries = self
+            .unchanged
+            .iter()
+            .chain(&self.add)
+            .chain(&self.retarget)
+            .map(|link| ManagedEntry {
+                name: link.name.to_string_lossy().to_string(),
+                source: link.source.to_string_lossy().to_string(),
+            })
+            .collect();
+
+        ManagedManifest { entries }
+    }
+}
+
+// Grouped by the same key the manifest matches entry names with
+fn name_key(name: &OsString) -> String {
+    managed_manifest::name_key(&name.to_string_lossy())
+}
+
+// Compare two folders file by file, e.g. a copied helper folder against its library folder
+fn same_dir_content(a: &Path, b: &Path) -> bool {
+    let (a_entries, b_entries) = match (fs::read_dir(a), fs::read_dir(b)) {
+        (Ok(a_entries), Ok(b_entries)) => (a_entries, b_entries),
+        _ => return false,
+    };
+
+    let mut a_names: Vec<OsString> = a_entries.flatten().map(|entry| entry.file_name()).collect();
+    let mut b_names: Vec<OsString> = b_entries.flatten().map(|entry| entry.file_name()).collect();
+    a_names.sort();
+    b_names.sort();
+
+    a_names == b_names
+        && a_names.iter().all(|name| {
+            let (a_path, b_path) = (a.join(name), b.join(name));
+            if a_path.is_dir() {
+                b_path.is_dir() && same_dir_content(&a_path, &b_path)
+            } else {
+                same_file_content(&a_path, &b_path)
+            }
+        })
+}
+
+fn same_file_content(a: &Path, b: &Path) -> bool {
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a_meta), Ok(b_meta)) if a_meta.len() == b_meta.len() => {
+            matches!((fs::read(a), fs::read(b)), (Ok(a_bytes), Ok(b_bytes)) if a_bytes == b_bytes)
+        }
+        _ => false,
+    }
+}
+
+/// Check whether an entry in the GSX folder already provides the given source.
+/// Symlinks must point at the source; hardlinks and copies must have the same content.
+/// Folder sources are matched by a directory link or a copied folder.
+pub fn entry_matches_source(entry: &Path, source: &Path) -> bool {
+    if entry.is_symlink() {
+        return match fs::read_link(entry) {
+            Ok(link_target) => {
+                let link_target = if link_target.is_relative() {
+                    entry.parent().unwrap_or(Path::new("")).join(link_target)
+                } else {
+                    link_target
+                };
+                link_target == source
+                    || matches!(
+                        (fs::canonicalize(&link_target), fs::canonicalize(source)),
+                        (Ok(a), Ok(b)) if a == b
+                    )
+            }
+            Err(_) => false,
+        };
+    }
+
+    if entry.is_dir() {
+        return source.is_dir() && same_dir_content(entry, source);
+    }
+
+    entry.is_file() && same_file_content(entry, source)
+}
+
+/// Compare the desired link set against the GSX folder. Only top-level entries are
+/// considered: files, symlinks and managed copied folders. User folders, backup folders and
+/// the manager's own folders are never touched.
+/// Entries the manager does not own are kept unless the ownership policy says otherwise.
+pub fn diff_link_set(
+    file_paths: &[String],
+    target_dir: &Path,
+    ownership: &Ownership,
+) -> Result<LinkDiff, String> {
+    let mut diff = LinkDiff::default();
+
+    // Desired links by file name; a later source with the same name wins, as before
+    let mut desired: Vec<DesiredLink> = Vec::new();
+    let mut desired_index: HashMap<String, usize> = HashMap::new();
+
+    for file_path in file_paths {
+        let source = PathBuf::from(file_path);
+
+        if !source.exists() {
+            diff.missing_sources.push(file_path.clone());
+            continue;
+        }
+
+        let name = source
+            .file_name()
+            .ok_or_else(|| format!("Could not get file name from path: {}", file_path))?
+            .to_os_string();
+        let link = DesiredLink {
+            source,
+            name: name.clone(),
+        };
+
+        match desired_index.get(&name_key(&name)) {
+            Some(&index) => desired[index] = link,
+            None => {
+                desired_index.insert(name_key(&name), desired.len());
+                desired.push(link);
+            }
+        }
+    }
+
+    // Existing entries by file name
+    let mut existing: HashMap<String, PathBuf> = HashMap::new();
+    if target_dir.exists() {
+        let entries = fs::read_dir(target_dir)
+            .map_err(|e| format!("Failed to read target directory: {}", e))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let path = entry.path();
+
+            if helpers::is_backup_dir(&path) || helpers::is_manager_dir(&path) {
+                continue;
+            }
+
+            // A real folder only counts if the manager copied it there
+            if path.is_file() || path.is_symlink() || ownership.is_managed_folder(&path) {
+                existing.insert(name_key(&entry.file_name()), path);
+            }
+        }
+    }
+
+    for link in desired {
+        let entry = match existing.remove(&name_key(&link.name)) {
+            Some(entry) => entry,
+            None => {
+                diff.add.push(link);
+                continue;
+            }
+        };
+
+        if entry_matches_source(&entry, &link.source) {
+            if ownership.owns(&entry) {
+                diff.unchanged.push(DesiredLink {
+                    source: link.source,
+                    name: entry.file_name().unwrap_or(&link.name).to_os_string(),
+                });
+            } else {
+                diff.preserved.push(entry);
+            }
+        } else if ownership.owns(&entry) || ownership.policy() == UnmanagedPolicy::Replace {
+            if !ownership.is_managed(&entry) {
+                diff.unmanaged_displaced.push(entry);
+            }
+            diff.retarget.push(link);
+        } else {
+            diff.blocked.push(entry);
+        }
+    }
+
+    // Whatever is left over is no longer selected; only owned entries are removed
+    for entry in existing.into_values() {
+        if ownership.owns(&entry) {
+            if !ownership.is_managed(&entry) {
+                diff.unmanaged_displaced.push(entry.clone());
+            }
+            diff.remove.push(entry);
+        } else {
+            diff.preserved.push(entry);
+        }
+    }
+
+    diff.remove.sort();
+    diff.preserved.sort();
+    diff.blocked.sort();
+    diff.unmanaged_displaced.sort();
+
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_grouped_the_way_the_manifest_matches_them() {
+        let mut manifest = ManagedManifest::default();
+        manifest.insert(ManagedEntry {
+            name: "ÉDDM.ini".to_string(),
+            source: String::new(),
+        });
+
+        let same_key =
+            name_key(&OsString::from("éddm.ini")) == name_key(&OsString::from("ÉDDM.ini"));
+
+        assert_eq!(manifest.is_managed("éddm.ini"), same_key);
+        assert_eq!(same_key, cfg!(windows));
+    }
+}
@@ -0,0 +1,398 @@
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle, Emitter};
+
+use crate::activation_diff::entry_matches_source;
+use crate::activation_history;
+use crate::activation_progress::ProgressReporter;
+use crate::activation_report::{ActivationError, ActivationReport};
+use crate::activation_transaction;
+use crate::create_profile_symlink::{self, helpers};
+use crate::gsx_target::get_gsx_target_dir;
+use crate::managed_manifest::{self, UnmanagedPolicy};
+use crate::profile_backups;
+use crate::settings::{self, BackendSettings};
+use crate::sim_guard::SimGuard;
+
+// Event emitted with the HealthReport when the startup check finds problems
+pub const HEALTH_EVENT: &str = "gsx-folder-health";
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthIssueKind {
+    Dangling, // The link or its library file no longer exists
+    Missing,  // An activated entry was removed from the GSX folder
+    Changed,  // The entry now points at or contains something else
+    Foreign,  // A file the manager did not activate; reported but never repaired
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HealthIssue {
+    pub kind: HealthIssueKind,
+    pub path: String,                    // Entry in the GSX folder
+    pub expected_source: Option<String>, // Library file from the last activation
+    pub actual_source: Option<String>,   // Where a symlink points now
+}
+
+/// How the GSX folder compares to the last activation
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct HealthReport {
+    pub target_dir: String,
+    pub checked_at: u64,    // Unix seconds
+    pub has_manifest: bool, // Without one, only symlinks can be checked
+    pub checked: usize,     // Activated entries that were checked
+    pub issues: Vec<HealthIssue>,
+}
+
+impl HealthReport {
+    // Issues that repair_gsx_folder can fix
+    pub fn needs_repair(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| issue.kind != HealthIssueKind::Foreign)
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RepairReport {
+    pub backup: Option<String>, // Backup of changed files taken before repairing
+    pub relinked: Vec<String>,
+    pub removed: Vec<String>, // Links whose library file is gone
+    pub health: HealthReport, // State after the repair
+}
+
+fn read_link_target(path: &Path) -> Option<String> {
+    fs::read_link(path)
+        .ok()
+        .map(|target| target.to_string_lossy().to_string())
+}
+
+/// Compare the GSX folder against the manifest written by the last activation
+pub fn check_health(target_dir: &Path) -> Result<HealthReport, String> {
+    let checked_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| format!("Failed to get system time: {}", e))?
+        .as_secs();
+
+    let mut report = HealthReport {
+        target_dir: target_dir.to_string_lossy().to_string(),
+        checked_at,
+        has_manifest: managed_manifest::load_manifest(target_dir)?.is_some(),
+        ..Default::default()
+    };
+
+    if !target_dir.exists() {
+        return Ok(report);
+    }
+
+    let manifest = managed_manifest::load_or_infer_manifest(target_dir)?;
+    report.checked = manifest.entries.len();
+
+    for entry in &manifest.entries {
+        let path = target_dir.join(&entry.name);
+        let source = Path::new(&entry.source);
+
+        let kind = if !path.exists() && !path.is_symlink() {
+            Some(HealthIssueKind::Missing)
+        } else if !path.exists() || !source.exists() {
+            // A symlink to nowhere, or a copy whose library file has been deleted
+            Some(HealthIssueKind::Dangling)
+        } else if report.has_manifest && !entry_matches_source(&path, source) {
+            Some(HealthIssueKind::Changed)
+        } else {
+            None
+        };
+
+        if let Some(kind) = kind {
+            report.issues.push(HealthIssue {
+                kind,
+                path: path.to_string_lossy().to_string(),
+                expected_source: report.has_manifest.then(|| entry.source.clone()),
+                actual_source: read_link_target(&path),
+            });
+        }
+    }
+
+    // Anything else at the top level was put there by someone else
+    let entries =
+        fs::read_dir(target_dir).map_err(|e| format!("Failed to read target directory: {}", e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+
+        if helpers::is_backup_dir(&path) || !(path.is_file() || path.is_symlink()) {
+            continue;
+        }
+
+        if !manifest.is_managed(&entry.file_name().to_string_lossy()) {
+            report.issues.push(HealthIssue {
+                kind: HealthIssueKind::Foreign,
+                path: path.to_string_lossy().to_string(),
+                expected_source: None,
+                actual_source: read_link_target(&path),
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// Bring the GSX folder back to the last activation. Missing and changed entries are
+/// linked again, links whose library file is gone are removed, and foreign files are left
+/// alone. Like an activation, it waits for or refuses a running simulator as `guard` says,
+/// and changed files are backed up first in the configured format, since GSX or the user may
+/// have edited them. Also returns the activation report for the history.
+pub fn repair_health(
+    target_dir: &Path,
+    settings: &BackendSettings,
+    guard: &SimGuard,
+    progress: &ProgressReporter,
+) -> Result<(RepairReport, ActivationReport), ActivationError> {
+    let started_at = Instant::now();
+    let sim_running = guard.check(None, progress)?;
+    let before = check_health(target_dir).map_err(ActivationError::target_dir)?;
+
+    let mut backup = None;
+    let has_changed_files = before.issues.iter().any(|issue| {
+        issue.kind == HealthIssueKind::Changed && !Path::new(&issue.path).is_symlink()
+    });
+    if has_changed_files {
+        let backup_dir =
+            create_profile_symlink::back_up_target_dir(target_dir, settings, &[], progress)
+                .map_err(ActivationError::backup)?;
+        backup = Some(backup_dir.to_string_lossy().to_string());
+    }
+
+    // Activating the last activation again restores it, and drops the sources that are gone
+    let sources =
+        activation_history::active_files(target_dir).map_err(ActivationError::target_dir)?;
+
+    let outcome = activation_transaction::activate_transactionally(
+        sources,
+        target_dir,
+        settings.link_strategy,
+        UnmanagedPolicy::Preserve,
+        progress,
+    )
+    .map_err(ActivationError::link)?;
+
+    let relinked = outcome
+        .delta
+        .added
+        .iter()
+        .chain(&outcome.delta.retargeted)
+        .cloned()
+        .collect();
+
+    let repair = RepairReport {
+        backup: backup.clone(),
+        relinked,
+        removed: outcome.delta.removed.clone(),
+        health: check_health(target_dir).map_err(ActivationError::target_dir)?,
+    };
+    let activation = ActivationReport {
+        target_dir: target_dir.to_string_lossy().to_string(),
+        linked: outcome.linked,
+        missing_sources: outcome.missing_sources,
+        removed: outcome.removed,
+        delta: outcome.delta,
+        backup,
+        sim_running,
+        duration_ms: started_at.elapsed().as_millis() as u64,
+        ..Default::default()
+    };
+
+    Ok((repair, activation))
+}
+
+// Called on startup so drift since the last run is reported before the next activation
+pub fn check_health_on_startup(app: &AppHandle) {
+    let target_dir = match get_gsx_target_dir(app) {
+        Ok(dir) => dir,
+        Err(e) => {
+            println!("Skipping GSX folder health check: {}", e);
+            return;
+        }
+    };
+
+    match check_health(&target_dir) {
+        Ok(report) if report.needs_repair() => {
+            println!(
+                "GSX folder has drifted from the last activation: {} issues",
+                report.issues.len()
+            );
+            if let Err(e) = app.emit(HEALTH_EVENT, &report) {
+                println!("Failed to emit health report: {}", e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => println!("Failed to check GSX folder health: {}", e),
+    }
+}
+
+#[command]
+pub fn check_gsx_health(app: AppHandle) -> Result<HealthReport, String> {
+    let target_dir = get_gsx_target_dir(&app)?;
+    check_health(&target_dir)
+}
+
+/// Repair the GSX folder like an activation: with the sim guard, the configured backup and
+/// an entry in the activation history
+#[command]
+pub async fn repair_gsx_folder(app: AppHandle) -> Result<RepairReport, ActivationError> {
+    let target_dir = get_gsx_target_dir(&app).map_err(ActivationError::target_dir)?;
+    let mut settings = settings::load_settings(&app).map_err(ActivationError::settings)?;
+
+    // repair_health has no app to find the default backup location with
+    let archive_dir =
+        profile_backups::get_archive_dir(&app, &settings).map_err(ActivationError::settings)?;
+    settings.backup_location = Some(archive_dir.to_string_lossy().to_string());
+    let progress = ProgressReporter::for_app(&app);
+    let guard = SimGuard::system(settings.sim_guard.clone());
+
+    // Queueing for the simulator waits, so keep it off the async worker threads
+    tauri::async_runtime::spawn_blocking(move || {
+        let previous_files = activation_history::active_files(&target_dir).unwrap_or_default();
+
+        let result = repair_health(&target_dir, &settings, &guard, &progress);
+        progress.finish();
+        let (report, activation) = result?;
+
+        activation_history::record_activation(&app, previous_files, &activation);
+        println!(
+            "Repaired GSX folder: {} relinked, {} removed, {} issues left",
+            report.relinked.len(),
+            report.removed.len(),
+            report.health.issues.len()
+        );
+
+        Ok(report)
+    })
+    .await
+    .map_err(|e| ActivationError::Link {
+        message: format!("Repair task failed: {}", e),
+    })?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link_strategy::LinkStrategy;
+    use crate::profile_backups::BackupFormat;
+    use crate::sim_guard::{ProcessLister, SimGuardSettings, SimRunningPolicy};
+    use std::path::PathBuf;
+
+    struct Processes(Vec<String>);
+
+    impl ProcessLister for Processes {
+        fn running_processes(&self) -> Result<Vec<String>, String> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn guard(running: &[&str], policy: SimRunningPolicy) -> SimGuard {
+        SimGuard::new(
+            Box::new(Processes(
+                running.iter().map(|name| name.to_string()).collect(),
+            )),
+            SimGuardSettings {
+                policy,
+                ..Default::default()
+            },
+        )
+    }
+
+    // A GSX folder with two activated profiles, one of them since overwritten with a real file
+    fn drifted() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("gsx-profiles");
+        let target = dir.path().join("GSX");
+        fs::create_dir_all(&library).unwrap();
+        fs::create_dir_all(&target).unwrap();
+
+        let sources = ["eddm-aerosoft.ini", "egll-justsim.ini"]
+            .iter()
+            .map(|name| {
+                fs::write(library.join(name), name).unwrap();
+                library.join(name).to_string_lossy().to_string()
+            })
+            .collect();
+        activation_transaction::activate_transactionally(
+            sources,
+            &target,
+            LinkStrategy::Auto,
+            UnmanagedPolicy::Preserve,
+            &ProgressReporter::default(),
+        )
+        .unwrap();
+
+        fs::remove_file(target.join("eddm-aerosoft.ini")).unwrap();
+        fs::write(target.join("eddm-aerosoft.ini"), "edited").unwrap();
+        fs::remove_file(target.join("egll-justsim.ini")).unwrap();
+
+        (dir, library, target)
+    }
+
+    #[test]
+    fn repair_backs_up_in_the_configured_format() {
+        let (dir, _library, target) = drifted();
+        let archive_dir = dir.path().join("backups");
+        let settings = BackendSettings {
+            backup_format: BackupFormat::Zip,
+            backup_location: Some(archive_dir.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+
+        let (repair, activation) = repair_health(
+            &target,
+            &settings,
+            &guard(&[], SimRunningPolicy::Refuse),
+            &ProgressReporter::default(),
+        )
+        .unwrap();
+
+        let backup = PathBuf::from(repair.backup.unwrap());
+        assert_eq!(backup.parent(), Some(archive_dir.as_path()));
+        assert!(backup.is_file());
+        assert!(!fs::read_dir(&target)
+            .unwrap()
+            .any(|entry| helpers::is_backup_dir(&entry.unwrap().path())));
+
+        assert_eq!(repair.relinked.len(), 2);
+        assert!(!repair.health.needs_repair());
+        // What the history records for the repair
+        assert_eq!(
+            activation.delta.added.len() + activation.delta.retargeted.len(),
+            2
+        );
+        assert_eq!(
+            activation.backup,
+            Some(backup.to_string_lossy().to_string())
+        );
+    }
+
+    #[test]
+    fn repair_respects_the_sim_guard() {
+        let (_dir, _library, target) = drifted();
+
+        let result = repair_health(
+            &target,
+            &BackendSettings::default(),
+            &guard(&["FlightSimulator.exe"], SimRunningPolicy::Refuse),
+            &ProgressReporter::default(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(ActivationError::SimulatorRunning { .. })
+        ));
+        assert_eq!(
+            fs::read_to_string(target.join("eddm-aerosoft.ini")).unwrap(),
+            "edited"
+        );
+        assert!(!target.join("egll-justsim.ini").exists());
+    }
+}
@@ -0,0 +1,432 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle};
+
+use crate::activation_diff::ActivationDelta;
+use crate::activation_progress::ProgressReporter;
+use crate::activation_report::{ActivationError, ActivationReport};
+use crate::activation_transaction;
+use crate::create_profile_symlink::{self, helpers};
+use crate::gsx_target::get_gsx_target_dir;
+use crate::managed_manifest::{self, UnmanagedPolicy};
+use crate::profile_backups;
+use crate::settings::{self, BackendSettings};
+use crate::sim_guard::SimGuard;
+
+const HISTORY_FILE_NAME: &str = "activation-history.json";
+
+// Older records are dropped once the history grows past this
+const MAX_HISTORY_ENTRIES: usize = 200;
+
+/// One activation as it was recorded after it succeeded
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ActivationRecord {
+    pub id: String,     // "activation-<unix millis>"
+    pub timestamp: u64, // Unix seconds
+    pub target_dir: String,
+    pub files: Vec<String>,   // Sources linked in the GSX folder afterwards
+    pub linked: Vec<String>,  // Sources this activation created links for
+    pub removed: Vec<String>, // Entries it removed or replaced
+    pub backup_id: Option<String>, // Backup taken before it, if any
+    pub previous_files: Vec<String>, // Sources linked before, restored by undo
+    pub undone: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+struct HistoryStore {
+    records: Vec<ActivationRecord>, // Oldest first
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UndoReport {
+    pub undone: Vec<String>, // Ids of the undone activations, newest first
+    pub files: Vec<String>,  // Sources linked after the undo
+    pub missing_sources: Vec<String>,
+    pub delta: ActivationDelta,
+    pub backup: Option<String>, // Location of the backup taken before the undo
+}
+
+// The history lives next to the settings in the app data folder
+fn get_history_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(settings::app_data_dir(app)?.join(HISTORY_FILE_NAME))
+}
+
+fn load_store(path: &Path) -> Result<HistoryStore, String> {
+    if !path.exists() {
+        return Ok(HistoryStore::default());
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read activation history: {}", e))?;
+
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse activation history: {}", e))
+}
+
+fn save_store(path: &Path, store: &HistoryStore) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create history directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(store)
+        .map_err(|e| format!("Failed to serialize activation history: {}", e))?;
+
+    fs::write(path, content).map_err(|e| format!("Failed to write activation history: {}", e))
+}
+
+/// The sources behind the links currently activated in the GSX folder
+pub fn active_files(target_dir: &Path) -> Result<Vec<String>, String> {
+    Ok(managed_manifest::load_or_infer_manifest(target_dir)?
+        .entries
+        .into_iter()
+        .map(|entry| entry.source)
+        .filter(|source| !source.is_empty())
+        .collect())
+}
+
+/// Build the record for a finished activation. `previous_files` must be read before it ran.
+pub fn new_record(
+    previous_files: Vec<String>,
+    report: &ActivationReport,
+) -> Result<ActivationRecord, String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| format!("Failed to get system time: {}", e))?;
+
+    Ok(ActivationRecord {
+        id: format!("activation-{}", now.as_millis()),
+        timestamp: now.as_secs(),
+        target_dir: report.target_dir.clone(),
+        files: active_files(Path::new(&report.target_dir))?,
+        linked: report
+            .linked
+            .iter()
+            .map(|file| file.source.clone())
+            .collect(),
+        removed: report.removed.clone(),
+        backup_id: report.backup.as_ref().and_then(|backup| {
+            Path::new(backup)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        }),
+        previous_files,
+        undone: false,
+    })
+}
+
+pub fn append_record(history_path: &Path, record: ActivationRecord) -> Result<(), String> {
+    let mut store = load_store(history_path)?;
+    store.records.push(record);
+
+    let excess = store.records.len().saturating_sub(MAX_HISTORY_ENTRIES);
+    store.records.drain(..excess);
+
+    save_store(history_path, &store)
+}
+
+/// All recorded activations, newest first
+pub fn list_history(history_path: &Path) -> Result<Vec<ActivationRecord>, String> {
+    let mut records = load_store(history_path)?.records;
+    records.reverse();
+    Ok(records)
+}
+
+/// Undo the last `count` activations of the GSX folder by linking what was active before the
+/// oldest of them. Like an activation this runs behind the sim guard, after backing up the
+/// files the user put in the folder. The undone records are marked as such rather than a new
+/// one being added, so undoing again goes further back.
+pub fn undo_activations(
+    history_path: &Path,
+    target_dir: &Path,
+    count: usize,
+    settings: &BackendSettings,
+    guard: &SimGuard,
+    progress: &ProgressReporter,
+) -> Result<UndoReport, ActivationError> {
+    let history_error = |message: String| ActivationError::History { message };
+    if count == 0 {
+        return Err(history_error("Nothing to undo".to_string()));
+    }
+
+    let mut store = load_store(history_path).map_err(history_error)?;
+    let target = target_dir.to_string_lossy().to_string();
+
+    // Indices of the activations that can still be undone, newest first
+    let undoable: Vec<usize> = (0..store.records.len())
+        .rev()
+        .filter(|&index| {
+            let record = &store.records[index];
+            !record.undone && record.target_dir == target
+        })
+        .take(count)
+        .collect();
+
+    if undoable.len() < count {
+        return Err(history_error(format!(
+            "Cannot undo {} activations, only {} recorded",
+            count,
+            undoable.len()
+        )));
+    }
+
+    let oldest = &store.records[undoable[count - 1]];
+    println!("Undoing {} activations back to before {}", count, oldest.id);
+
+    guard.check(None, progress)?;
+
+    let mut backup = None;
+    if helpers::has_actual_files(target_dir).map_err(ActivationError::target_dir)? {
+        let backup_dir =
+            create_profile_symlink::back_up_target_dir(target_dir, settings, &[], progress)
+                .map_err(ActivationError::backup)?;
+        backup = Some(backup_dir.to_string_lossy().to_string());
+    }
+
+    // Files the user replaced meanwhile are never overwritten by an undo
+    let outcome = activation_transaction::activate_transactionally(
+        oldest.previous_files.clone(),
+        target_dir,
+        settings.link_strategy,
+        UnmanagedPolicy::Preserve,
+        progress,
+    )
+    .map_err(ActivationError::link)?;
+
+    let mut undone = Vec::new();
+    for index in undoable {
+        store.records[index].undone = true;
+        undone.push(store.records[index].id.clone());
+    }
+    save_store(history_path, &store).map_err(history_error)?;
+
+    Ok(UndoReport {
+        undone,
+        files: active_files(target_dir).map_err(ActivationError::target_dir)?,
+        missing_sources: outcome.missing_sources,
+        delta: outcome.delta,
+        backup,
+    })
+}
+
+// Called by activate_profiles; a history that cannot be written never fails an activation
+pub fn record_activation(app: &AppHandle, previous_files: Vec<String>, report: &ActivationReport) {
+    let result = get_history_path(app).and_then(|history_path| {
+        let record = new_record(previous_files, report)?;
+        println!("Recording activation {}", record.id);
+        append_record(&history_path, record)
+    });
+
+    if let Err(e) = result {
+        println!("Failed to record activation: {}", e);
+    }
+}
+
+#[command]
+pub fn list_activation_history(app: AppHandle) -> Result<Vec<ActivationRecord>, String> {
+    list_history(&get_history_path(&app)?)
+}
+
+/// Undo the last `count` activations, one if left out
+#[command]
+pub async fn undo_activation(
+    app: AppHandle,
+    count: Option<usize>,
+) -> Result<UndoReport, ActivationError> {
+    let target_dir = get_gsx_target_dir(&app).map_err(ActivationError::target_dir)?;
+    let history_path = get_history_path(&app).map_err(ActivationError::settings)?;
+    let mut settings = settings::load_settings(&app).map_err(ActivationError::settings)?;
+
+    // undo_activations has no app to find the default backup location with
+    let archive_dir =
+        profile_backups::get_archive_dir(&app, &settings).map_err(ActivationError::settings)?;
+    settings.backup_location = Some(archive_dir.to_string_lossy().to_string());
+    let progress = ProgressReporter::for_app(&app);
+    let guard = SimGuard::system(settings.sim_guard.clone());
+
+    // Queueing for the simulator waits, so keep it off the async worker threads
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = undo_activations(
+            &history_path,
+            &target_dir,
+            count.unwrap_or(1),
+            &settings,
+            &guard,
+            &progress,
+        );
+        progress.finish();
+        result
+    })
+    .await
+    .map_err(|e| ActivationError::Link {
+        message: format!("Undo task failed: {}", e),
+    })?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link_strategy::LinkStrategy;
+    use crate::sim_guard::{ProcessLister, SimGuardSettings, SimRunningPolicy};
+
+    struct Processes(Vec<String>);
+
+    impl ProcessLister for Processes {
+        fn running_processes(&self) -> Result<Vec<String>, String> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn guard(running: &[&str]) -> SimGuard {
+        SimGuard::new(
+            Box::new(Processes(
+                running.iter().map(|name| name.to_string()).collect(),
+            )),
+            SimGuardSettings {
+                policy: SimRunningPolicy::Refuse,
+                ..Default::default()
+            },
+        )
+    }
+
+    struct Fixture {
+        _dir: tempfile::TempDir,
+        library: PathBuf,
+        target: PathBuf,
+        history: PathBuf,
+    }
+
+    fn fixture() -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("gsx-profiles");
+        let target = dir.path().join("GSX");
+        fs::create_dir_all(&library).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        for name in ["eddm.ini", "egll.ini"] {
+            fs::write(library.join(name), name).unwrap();
+        }
+
+        Fixture {
+            history: dir.path().join(HISTORY_FILE_NAME),
+            _dir: dir,
+            library,
+            target,
+        }
+    }
+
+    fn sources(fixture: &Fixture, names: &[&str]) -> Vec<String> {
+        names
+            .iter()
+            .map(|name| fixture.library.join(name).to_string_lossy().to_string())
+            .collect()
+    }
+
+    // Activate and record it, as activate_profiles does
+    fn activate(fixture: &Fixture, names: &[&str]) {
+        let previous_files = active_files(&fixture.target).unwrap();
+        let outcome = activation_transaction::activate_transactionally(
+            sources(fixture, names),
+            &fixture.target,
+            LinkStrategy::Auto,
+            UnmanagedPolicy::Preserve,
+            &ProgressReporter::default(),
+        )
+        .unwrap();
+        let report = ActivationReport {
+            target_dir: fixture.target.to_string_lossy().to_string(),
+            removed: outcome.removed,
+            ..Default::default()
+        };
+        append_record(
+            &fixture.history,
+            new_record(previous_files, &report).unwrap(),
+        )
+        .unwrap();
+    }
+
+    fn undo(
+        fixture: &Fixture,
+        count: usize,
+        running: &[&str],
+    ) -> Result<UndoReport, ActivationError> {
+        undo_activations(
+            &fixture.history,
+            &fixture.target,
+            count,
+            &BackendSettings::default(),
+            &guard(running),
+            &ProgressReporter::default(),
+        )
+    }
+
+    fn undone_flags(fixture: &Fixture) -> Vec<bool> {
+        load_store(&fixture.history)
+            .unwrap()
+            .records
+            .iter()
+            .map(|record| record.undone)
+            .collect()
+    }
+
+    #[test]
+    fn undo_goes_back_count_activations_of_the_folder() {
+        let fixture = fixture();
+        activate(&fixture, &["eddm.ini"]);
+        activate(&fixture, &["eddm.ini", "egll.ini"]);
+        activate(&fixture, &["egll.ini"]);
+        // Another GSX folder's activation is never undone from here
+        let other = ActivationRecord {
+            id: "activation-1".to_string(),
+            target_dir: "/other/GSX".to_string(),
+            ..Default::default()
+        };
+        append_record(&fixture.history, other).unwrap();
+
+        let report = undo(&fixture, 2, &[]).unwrap();
+
+        assert_eq!(report.undone.len(), 2);
+        assert_eq!(report.files, sources(&fixture, &["eddm.ini"]));
+        assert_eq!(active_files(&fixture.target).unwrap(), report.files);
+        assert_eq!(undone_flags(&fixture), vec![false, true, true, false]);
+
+        // Undoing again goes further back
+        let report = undo(&fixture, 1, &[]).unwrap();
+        assert!(report.files.is_empty());
+        assert_eq!(undone_flags(&fixture), vec![true, true, true, false]);
+    }
+
+    #[test]
+    fn undo_fails_without_enough_records() {
+        let fixture = fixture();
+        activate(&fixture, &["eddm.ini"]);
+        activate(&fixture, &["egll.ini"]);
+
+        let error = undo(&fixture, 3, &[]).unwrap_err();
+
+        assert!(matches!(error, ActivationError::History { .. }));
+        assert_eq!(undone_flags(&fixture), vec![false, false]);
+        assert_eq!(
+            active_files(&fixture.target).unwrap(),
+            sources(&fixture, &["egll.ini"])
+        );
+    }
+
+    #[test]
+    fn undo_waits_for_the_sim_guard() {
+        let fixture = fixture();
+        activate(&fixture, &["eddm.ini"]);
+        activate(&fixture, &["egll.ini"]);
+
+        let error = undo(&fixture, 1, &["FlightSimulator.exe"]).unwrap_err();
+
+        assert!(matches!(error, ActivationError::SimulatorRunning { .. }));
+        assert_eq!(undone_flags(&fixture), vec![false, false]);
+        assert_eq!(
+            active_files(&fixture.target).unwrap(),
+            sources(&fixture, &["egll.ini"])
+        );
+    }
+}
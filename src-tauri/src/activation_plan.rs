@@ -0,0 +1,221 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{command, AppHandle};
+
+use crate::activation_diff;
+use crate::gsx_target::get_gsx_target_dir;
+use crate::managed_manifest::{Ownership, UnmanagedPolicy};
+use crate::profile_conflicts::{self, ConflictPolicy, ConflictReport, ProfileConflict};
+use crate::settings;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PlannedLink {
+    pub source: String,
+    pub target: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ActivationPlan {
+    pub target_dir: String,
+    pub removals: Vec<String>, // Entries that would be removed or retargeted
+    pub overwrites: Vec<String>, // Real files (not symlinks) among the removals
+    pub links: Vec<PlannedLink>, // Links that would be created or retargeted
+    pub unchanged: Vec<String>, // Entries that already point at a selected source
+    pub preserved: Vec<String>, // Files the manager does not own and would leave alone
+    pub blocked: Vec<String>,  // Unmanaged files a selected profile would have to replace
+    pub missing_sources: Vec<String>,
+    pub conflicts: Vec<ProfileConflict>, // As the conflict policy would resolve them
+    pub dropped: Vec<String>,            // Files the conflict policy would leave out
+    pub conflict_error: Option<String>,  // Why the activation would fail under Fail
+    pub requires_confirmation: bool,     // Activating would ask before touching unmanaged files
+}
+
+/// Build the activation plan for the given profile files without touching the target folder.
+/// Resolves conflicts and diffs like activate_profiles: backup folders are left alone, links
+/// that already point at a selected source are kept, and everything else is added, retargeted
+/// or removed. Files the manager does not own are only touched as the unmanaged policy allows.
+/// Without an explicit policy the activation asks before replacing blocked files, and the plan
+/// says so.
+pub fn build_activation_plan(
+    file_paths: &[String],
+    target_dir: &Path,
+    conflict_policy: &ConflictPolicy,
+    unmanaged_policy: Option<UnmanagedPolicy>,
+) -> Result<ActivationPlan, String> {
+    // Under Fail the activation stops here; the rest of the plan shows the raw selection
+    let (resolution, conflict_error) =
+        match profile_conflicts::resolve_conflicts(file_paths, conflict_policy) {
+            Ok(resolution) => (resolution, None),
+            Err(message) => (
+                ConflictReport {
+                    conflicts: profile_conflicts::detect_conflicts(file_paths),
+                    kept_files: file_paths.to_vec(),
+                    dropped_files: Vec::new(),
+                },
+                Some(message),
+            ),
+        };
+
+    let ownership = Ownership::load(target_dir, unmanaged_policy.unwrap_or_default())?;
+    let diff = activation_diff::diff_link_set(&resolution.kept_files, target_dir, &ownership)?;
+
+    let mut removals = Vec::new();
+    let mut overwrites = Vec::new();
+
+    for path in diff.entries_to_displace(target_dir) {
+        if path.is_file() && !path.is_symlink() {
+            overwrites.push(path.to_string_lossy().to_string());
+        }
+        removals.push(path.to_string_lossy().to_string());
+    }
+
+    removals.sort();
+    overwrites.sort();
+
+    let links = diff
+        .add
+        .iter()
+        .chain(&diff.retarget)
+        .map(|link| PlannedLink {
+            source: link.source.to_string_lossy().to_string(),
+            target: target_dir.join(&link.name).to_string_lossy().to_string(),
+        })
+        .collect();
+
+    let to_strings = |paths: &[PathBuf]| {
+        paths
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect::<Vec<String>>()
+    };
+
+    Ok(ActivationPlan {
+        target_dir: target_dir.to_string_lossy().to_string(),
+        removals,
+        overwrites,
+        links,
+        unchanged: diff
+            .unchanged
+            .iter()
+            .map(|link| target_dir.join(&link.name).to_string_lossy().to_string())
+            .collect(),
+        preserved: to_strings(&diff.preserved),
+        blocked: to_strings(&diff.blocked),
+        missing_sources: diff.missing_sources,
+        conflicts: resolution.conflicts,
+        dropped: resolution.dropped_files,
+        conflict_error,
+        requires_confirmation: !diff.unmanaged_displaced.is_empty()
+            || (!diff.blocked.is_empty() && unmanaged_policy.is_none()),
+    })
+}
+
+#[command]
+pub fn plan_profile_activation(
+    app: AppHandle,
+    selected_files: Vec<String>,
+    conflict_policy: Option<ConflictPolicy>,
+    unmanaged_policy: Option<UnmanagedPolicy>,
+) -> Result<ActivationPlan, String> {
+    let target_dir = get_gsx_target_dir(&app)?;
+    let conflict_policy = match conflict_policy {
+        Some(policy) => policy,
+        None => settings::load_settings(&app)?.conflict_policy,
+    };
+
+    let plan = build_activation_plan(
+        &selected_files,
+        &target_dir,
+        &conflict_policy,
+        unmanaged_policy,
+    )?;
+
+    println!(
+        "Activation plan: {} removals ({} real files), {} links, {} unchanged, {} preserved, {} blocked, {} missing sources",
+        plan.removals.len(),
+        plan.overwrites.len(),
+        plan.links.len(),
+        plan.unchanged.len(),
+        plan.preserved.len(),
+        plan.blocked.len(),
+        plan.missing_sources.len()
+    );
+
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn setup() -> (tempfile::TempDir, Vec<String>, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("GSX");
+        fs::create_dir_all(&target).unwrap();
+
+        // Two developers' profiles with the same file name
+        let files = ["aerosoft", "justsim"]
+            .iter()
+            .map(|developer| {
+                let path = dir.path().join(developer).join("eddm.ini");
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, developer).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+
+        (dir, files, target)
+    }
+
+    #[test]
+    fn plan_applies_the_conflict_policy() {
+        let (_dir, files, target) = setup();
+
+        let plan =
+            build_activation_plan(&files, &target, &ConflictPolicy::KeepFirst, None).unwrap();
+
+        assert_eq!(plan.links.len(), 1);
+        assert_eq!(plan.links[0].source, files[0]);
+        assert_eq!(plan.dropped, vec![files[1].clone()]);
+        assert_eq!(plan.conflicts.len(), 1);
+        assert!(plan.conflicts[0].kept.is_some());
+        assert_eq!(plan.conflict_error, None);
+    }
+
+    #[test]
+    fn plan_reports_that_fail_would_stop_the_activation() {
+        let (_dir, files, target) = setup();
+
+        let plan = build_activation_plan(&files, &target, &ConflictPolicy::Fail, None).unwrap();
+
+        assert!(plan.conflict_error.is_some());
+        assert!(plan
+            .conflicts
+            .iter()
+            .all(|conflict| conflict.kept.is_none()));
+        assert!(plan.dropped.is_empty());
+    }
+
+    #[test]
+    fn plan_asks_before_replacing_blocked_files_without_a_policy() {
+        let (_dir, files, target) = setup();
+        fs::write(target.join("eddm.ini"), "hand placed").unwrap();
+        let selected = &files[..1];
+
+        let plan =
+            build_activation_plan(selected, &target, &ConflictPolicy::KeepFirst, None).unwrap();
+        assert_eq!(plan.blocked.len(), 1);
+        assert!(plan.requires_confirmation);
+
+        let plan = build_activation_plan(
+            selected,
+            &target,
+            &ConflictPolicy::KeepFirst,
+            Some(UnmanagedPolicy::Preserve),
+        )
+        .unwrap();
+        assert_eq!(plan.blocked.len(), 1);
+        assert!(!plan.requires_confirmation);
+    }
+}
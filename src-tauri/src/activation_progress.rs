@@ -0,0 +1,206 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tauri::{command, AppHandle, Emitter, Manager};
+
+// Event emitted with an ActivationProgress payload while an activation runs
+pub const PROGRESS_EVENT: &str = "activation-progress";
+
+/// Progress of an activation, sent to the frontend as it happens
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum ActivationProgress {
+    Started {
+        total: usize, // Selected files
+    },
+    // Queued until the simulator exits; sent again on every check
+    WaitingForSimulator {
+        processes: Vec<String>,
+    },
+    BackupCopied {
+        current: usize,
+        total: usize,
+        file: String,
+    },
+    Linked {
+        current: usize,
+        total: usize, // Links this activation creates, unchanged ones are not counted
+        source: String,
+    },
+    Finished {
+        duration_ms: u64,
+    },
+    Cancelled,
+    Failed {
+        message: String,
+    },
+}
+
+/// Cancellation tokens of the running activations, by activation id. Each activation gets
+/// its own, so a cancel only ever reaches the activation it was meant for. Registered as
+/// managed state in main.
+#[derive(Default)]
+pub struct ActivationCancellation {
+    next_id: AtomicU64,
+    tokens: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl ActivationCancellation {
+    fn register(&self) -> (String, Arc<AtomicBool>) {
+        let id = format!(
+            "activation-{}",
+            self.next_id.fetch_add(1, Ordering::SeqCst) + 1
+        );
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        self.lock().insert(id.clone(), cancel.clone());
+        (id, cancel)
+    }
+
+    fn remove(&self, id: &str) {
+        self.lock().remove(id);
+    }
+
+    // Returns false when no activation with this id is running
+    fn cancel(&self, id: &str) -> bool {
+        match self.lock().get(id) {
+            Some(cancel) => {
+                cancel.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // A panic while holding the lock leaves the map itself intact
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Arc<AtomicBool>>> {
+        self.tokens
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// The payload of PROGRESS_EVENT: the progress with the id cancel_activation takes
+#[derive(Serialize)]
+struct ProgressEvent<'a> {
+    activation_id: &'a str,
+    #[serde(flatten)]
+    progress: &'a ActivationProgress,
+}
+
+/// Passed down to the activation steps to report progress and check for cancellation.
+/// The default reporter does neither, for callers without a UI.
+#[derive(Default, Clone)]
+pub struct ProgressReporter {
+    app: Option<AppHandle>,
+    id: Option<String>,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl ProgressReporter {
+    /// Emit events to the app's windows and honour cancel_activation for a new activation
+    /// id. Call finish once the activation is over.
+    pub fn for_app(app: &AppHandle) -> ProgressReporter {
+        let (id, cancel) = app.state::<ActivationCancellation>().register();
+
+        ProgressReporter {
+            app: Some(app.clone()),
+            id: Some(id),
+            cancel: Some(cancel),
+        }
+    }
+
+    // A reporter without an app whose cancellation a test controls
+    #[cfg(test)]
+    pub fn with_cancel(cancel: Arc<AtomicBool>) -> ProgressReporter {
+        ProgressReporter {
+            app: None,
+            id: None,
+            cancel: Some(cancel),
+        }
+    }
+
+    /// Drop the activation's cancellation token
+    pub fn finish(&self) {
+        if let (Some(app), Some(id)) = (&self.app, &self.id) {
+            app.state::<ActivationCancellation>().remove(id);
+        }
+    }
+
+    pub fn report(&self, progress: ActivationProgress) {
+        if let (Some(app), Some(id)) = (&self.app, &self.id) {
+            let event = ProgressEvent {
+                activation_id: id,
+                progress: &progress,
+            };
+            if let Err(e) = app.emit(PROGRESS_EVENT, &event) {
+                println!("Failed to emit activation progress: {}", e);
+            }
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::SeqCst))
+    }
+
+    // Fail with a cancellation error if the user asked to stop
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            return Err("Activation cancelled by user".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Ask the activation with this id, as sent with its progress events, to stop. It stops
+/// before the GSX folder is changed, or finishes if the new links are already being moved in.
+#[command]
+pub fn cancel_activation(app: AppHandle, activation_id: String) -> Result<(), String> {
+    println!("Cancelling activation {}", activation_id);
+
+    if !app.state::<ActivationCancellation>().cancel(&activation_id) {
+        return Err(format!("No running activation {}", activation_id));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancels_only_the_activation_asked_for() {
+        let cancellation = ActivationCancellation::default();
+        let (first_id, first) = cancellation.register();
+        let (second_id, second) = cancellation.register();
+        assert_ne!(first_id, second_id);
+
+        // Cancelled while it waits for the simulator, before the second one starts
+        assert!(cancellation.cancel(&first_id));
+        let (_, third) = cancellation.register();
+
+        assert!(first.load(Ordering::SeqCst));
+        assert!(!second.load(Ordering::SeqCst));
+        assert!(!third.load(Ordering::SeqCst));
+
+        cancellation.remove(&first_id);
+        assert!(!cancellation.cancel(&first_id));
+    }
+
+    #[test]
+    fn events_carry_the_activation_id() {
+        let event = ProgressEvent {
+            activation_id: "activation-1",
+            progress: &ActivationProgress::Started { total: 2 },
+        };
+
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({"activation_id": "activation-1", "stage": "started", "total": 2})
+        );
+    }
+}
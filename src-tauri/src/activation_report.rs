@@ -0,0 +1,134 @@
+use serde::Serialize;
+use std::fmt;
+
+use crate::activation_diff::ActivationDelta;
+use crate::link_strategy::LinkStrategy;
+use crate::profile_conflicts::ProfileConflict;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LinkedFile {
+    pub source: String,
+    pub target: String,
+    pub strategy: LinkStrategy, // The strategy actually used, never Auto
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SkippedSource {
+    pub source: String,
+    pub reason: String,
+}
+
+// What linking the selected files changed, filled in by the helpers and the transaction
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct LinkOutcome {
+    pub linked: Vec<LinkedFile>,
+    pub missing_sources: Vec<String>,
+    pub removed: Vec<String>, // Previous entries that were removed or replaced
+    pub delta: ActivationDelta,
+}
+
+/// Result of a successful activation, returned to the frontend
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ActivationReport {
+    pub target_dir: String,
+    pub linked: Vec<LinkedFile>,     // Links created by this activation
+    pub skipped: Vec<SkippedSource>, // Sources dropped by the conflict policy
+    pub missing_sources: Vec<String>,
+    pub removed: Vec<String>,
+    pub delta: ActivationDelta,
+    pub conflicts: Vec<ProfileConflict>,
+    pub backup: Option<String>, // Location of the backup taken before activation
+    pub sim_running: Vec<String>, // Simulator processes that were running, with the Warn policy
+    pub duration_ms: u64,
+}
+
+/// Why an activation failed. Serialized with a `kind` tag so the frontend can react to
+/// each case without matching on the message text.
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ActivationError {
+    // The user dismissed the confirmation dialog or cancelled the activation
+    Cancelled,
+    // The GSX folder could not be resolved or created
+    TargetDir {
+        message: String,
+    },
+    Settings {
+        message: String,
+    },
+    Conflict {
+        message: String,
+        conflicts: Vec<ProfileConflict>,
+    },
+    // The named loadout does not exist
+    Loadout {
+        message: String,
+    },
+    // The route has no airports, or no profiles for any of them
+    Route {
+        message: String,
+    },
+    // There are not as many recorded activations as an undo asked for
+    History {
+        message: String,
+    },
+    // Selected profiles would replace files the manager does not own
+    UnmanagedFiles {
+        message: String,
+        files: Vec<String>,
+    },
+    // The simulator or GSX is running and the policy refuses to activate
+    SimulatorRunning {
+        message: String,
+        processes: Vec<String>,
+    },
+    Dialog {
+        message: String,
+    },
+    Backup {
+        message: String,
+    },
+    // Linking failed; the previous profiles were restored unless the message says otherwise
+    Link {
+        message: String,
+    },
+}
+
+impl ActivationError {
+    pub fn target_dir(message: String) -> ActivationError {
+        ActivationError::TargetDir { message }
+    }
+
+    pub fn settings(message: String) -> ActivationError {
+        ActivationError::Settings { message }
+    }
+
+    pub fn backup(message: String) -> ActivationError {
+        ActivationError::Backup { message }
+    }
+
+    pub fn link(message: String) -> ActivationError {
+        ActivationError::Link { message }
+    }
+}
+
+impl fmt::Display for ActivationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActivationError::Cancelled => write!(f, "Operation cancelled by user."),
+            ActivationError::TargetDir { message }
+            | ActivationError::Settings { message }
+            | ActivationError::Conflict { message, .. }
+            | ActivationError::Loadout { message }
+            | ActivationError::Route { message }
+            | ActivationError::History { message }
+            | ActivationError::UnmanagedFiles { message, .. }
+            | ActivationError::SimulatorRunning { message, .. }
+            | ActivationError::Dialog { message }
+            | ActivationError::Backup { message }
+            | ActivationError::Link { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ActivationError {}
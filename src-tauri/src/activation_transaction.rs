@@ -0,0 +1,574 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::activation_diff;
+use crate::activation_progress::ProgressReporter;
+use crate::activation_report::LinkOutcome;
+use crate::backup_store;
+use crate::create_profile_symlink::helpers;
+use crate::gsx_target::get_gsx_target_dir;
+use crate::link_strategy::LinkStrategy;
+use crate::managed_manifest::{self, ManagedManifest, Ownership, UnmanagedPolicy};
+
+// Folders inside the GSX target directory used while an activation is in flight.
+// Both start with ".gsx-", so the helpers never back them up or remove them.
+const STAGING_DIR_NAME: &str = ".gsx-activation-staging";
+const ROLLBACK_DIR_NAME: &str = ".gsx-activation-rollback";
+const JOURNAL_FILE_NAME: &str = "journal.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum JournalPhase {
+    // Previous entries are being moved out of the target directory
+    Displacing,
+    // Previous entries are all in the rollback folder, staged links are being moved in
+    Installing,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ActivationJournal {
+    phase: JournalPhase,
+    // File names of the staged links that are swapped into the target directory
+    installed: Vec<String>,
+    // Whether a manifest existed before; its copy is kept in the rollback folder
+    #[serde(default)]
+    had_manifest: bool,
+}
+
+fn staging_dir(target_dir: &Path) -> PathBuf {
+    target_dir.join(STAGING_DIR_NAME)
+}
+
+fn rollback_dir(target_dir: &Path) -> PathBuf {
+    target_dir.join(ROLLBACK_DIR_NAME)
+}
+
+fn rollback_entries_dir(target_dir: &Path) -> PathBuf {
+    rollback_dir(target_dir).join("entries")
+}
+
+fn manifest_backup_path(target_dir: &Path) -> PathBuf {
+    rollback_dir(target_dir).join("manifest.json")
+}
+
+fn journal_path(target_dir: &Path) -> PathBuf {
+    rollback_dir(target_dir).join(JOURNAL_FILE_NAME)
+}
+
+fn write_journal(target_dir: &Path, journal: &ActivationJournal) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(journal)
+        .map_err(|e| format!("Failed to serialize activation journal: {}", e))?;
+
+    // Written under a temporary name so a crash never leaves a truncated journal behind
+    let path = journal_path(target_dir);
+    let partial_path = path.with_extension("partial");
+    fs::write(&partial_path, content)
+        .map_err(|e| format!("Failed to write activation journal: {}", e))?;
+    fs::rename(&partial_path, &path)
+        .map_err(|e| format!("Failed to write activation journal: {}", e))
+}
+
+fn read_journal(target_dir: &Path) -> Result<Option<ActivationJournal>, String> {
+    let path = journal_path(target_dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read activation journal: {}", e))?;
+    let journal = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse activation journal: {}", e))?;
+
+    Ok(Some(journal))
+}
+
+/// Activate profiles as a single all-or-nothing operation.
+/// Only what differs from the GSX folder is changed: links for new or moved sources are
+/// created in a staging folder first, then the entries being removed or retargeted are moved
+/// aside and the staged links are moved in. Unchanged links are never touched. If any step
+/// fails, the previous state is restored and the error is returned. The journal written
+/// during the swap lets recover_activation restore the previous state if the app dies halfway.
+/// Removing it commits the activation.
+pub fn activate_transactionally(
+    file_paths: Vec<String>,
+    target_dir: &Path,
+    strategy: LinkStrategy,
+    unmanaged_policy: UnmanagedPolicy,
+    progress: &ProgressReporter,
+) -> Result<LinkOutcome, String> {
+    // Finish off a previous activation that never completed before starting a new one
+    recover_activation(target_dir)?;
+
+    let ownership = Ownership::load(target_dir, unmanaged_policy)?;
+    let diff = activation_diff::diff_link_set(&file_paths, target_dir, &ownership)?;
+    let delta = diff.delta(target_dir);
+    let manifest = diff.managed_manifest();
+
+    if !diff.blocked.is_empty() {
+        return Err(format!(
+            "Selected profiles would replace files the manager does not own: {:?}",
+            diff.blocked
+        ));
+    }
+
+    if !diff.has_changes() {
+        // Still record ownership, e.g. for folders activated before the manifest existed
+        if managed_manifest::load_manifest(target_dir)?.as_ref() != Some(&manifest) {
+            managed_manifest::save_manifest(target_dir, &manifest)?;
+        }
+
+        println!("GSX profiles are already up to date");
+        return Ok(LinkOutcome {
+            missing_sources: diff.missing_sources,
+            delta,
+            ..Default::default()
+        });
+    }
+
+    let staging = staging_dir(target_dir);
+    if staging.exists() {
+        helpers::remove_entry(&staging)?;
+    }
+    fs::create_dir_all(&staging)
+        .map_err(|e| format!("Failed to create staging directory: {}", e))?;
+
+    // Stage the new link set. Nothing in the target directory has changed yet, so this is
+    // the last point where a cancellation can stop the activation.
+    let sources_to_link = diff.sources_to_link();
+    let staged = helpers::create_profile_symlinks(sources_to_link, &staging, strategy, progress)
+        .and_then(|outcome| progress.check_cancelled().map(|_| outcome));
+    let mut outcome = match staged {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            let _ = helpers::remove_entry(&staging);
+            return Err(e);
+        }
+    };
+
+    outcome.missing_sources = diff.missing_sources.clone();
+    outcome.delta = delta;
+
+    let displaced = diff.entries_to_displace(target_dir);
+    outcome.removed = swap_and_commit(target_dir, &displaced, &manifest)?;
+
+    // The links were created in the staging folder, report where they ended up
+    for linked in &mut outcome.linked {
+        if let Some(file_name) = Path::new(&linked.target).file_name() {
+            linked.target = target_dir.join(file_name).to_string_lossy().to_string();
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// Put the files of a backup back into the GSX folder as a single all-or-nothing operation.
+/// The files are written to the staging folder first, then the manager's links and whatever
+/// is in the way of a restored file are moved aside and the staged files are moved in. A
+/// folder in the way is staged with its current files, so only the backed up ones change.
+/// Other files the user put there are left alone. Returns the entries that were replaced.
+pub fn restore_transactionally(
+    files: &[(String, Vec<u8>)],
+    target_dir: &Path,
+) -> Result<Vec<String>, String> {
+    recover_activation(target_dir)?;
+
+    let staging = staging_dir(target_dir);
+    if staging.exists() {
+        helpers::remove_entry(&staging)?;
+    }
+    fs::create_dir_all(&staging)
+        .map_err(|e| format!("Failed to create staging directory: {}", e))?;
+
+    let displaced = stage_restored_files(files, target_dir).and_then(|top_level| {
+        let mut displaced = helpers::managed_entries(target_dir)?;
+        for name in top_level {
+            let path = target_dir.join(name);
+            if (path.exists() || path.is_symlink()) && !displaced.contains(&path) {
+                displaced.push(path);
+            }
+        }
+        Ok(displaced)
+    });
+    let displaced = match displaced {
+        Ok(displaced) => displaced,
+        Err(e) => {
+            let _ = helpers::remove_entry(&staging);
+            return Err(e);
+        }
+    };
+
+    // Restored files are real files again, so the manager owns nothing afterwards
+    swap_and_commit(target_dir, &displaced, &ManagedManifest::default())
+}
+
+// Write the restored files into the staging folder and return the names of the entries
+// they need in the target directory
+fn stage_restored_files(
+    files: &[(String, Vec<u8>)],
+    target_dir: &Path,
+) -> Result<Vec<String>, String> {
+    let staging = staging_dir(target_dir);
+    let ownership = Ownership::load(target_dir, UnmanagedPolicy::Preserve)?;
+    let mut top_level: Vec<String> = Vec::new();
+
+    for (name, _) in files {
+        // Validates the name, so it never leaves the folder
+        backup_store::restore_path(&staging, name)?;
+        let first = name.split('/').next().unwrap_or(name).to_string();
+        if top_level.contains(&first) {
+            continue;
+        }
+
+        // Keep the other files of a user folder the backup writes into
+        let existing = target_dir.join(&first);
+        if existing.is_dir() && !existing.is_symlink() && !ownership.is_managed_folder(&existing) {
+            for path in helpers::collect_real_files(&existing)? {
+                let relative = helpers::relative_name(target_dir, &path);
+                fs::copy(&path, backup_store::restore_path(&staging, &relative)?)
+                    .map_err(|e| format!("Failed to stage {:?}: {}", path, e))?;
+            }
+        }
+        top_level.push(first);
+    }
+
+    for (name, content) in files {
+        println!("Restoring file: {}", name);
+        fs::write(backup_store::restore_path(&staging, name)?, content)
+            .map_err(|e| format!("Failed to restore {}: {}", name, e))?;
+    }
+
+    Ok(top_level)
+}
+
+// Swap the staged entries in and commit, or restore the previous entries after a failure.
+// Returns the entries that were moved aside.
+fn swap_and_commit(
+    target_dir: &Path,
+    displaced: &[PathBuf],
+    manifest: &ManagedManifest,
+) -> Result<Vec<String>, String> {
+    let swapped = swap_in_staged_links(target_dir, displaced, manifest)
+        .and_then(|removed| commit(target_dir).map(|_| removed));
+    let removed = match swapped {
+        Ok(removed) => removed,
+        Err(e) => {
+            println!("Activation failed, restoring previous profiles: {}", e);
+            let recovery = recover_activation(target_dir);
+            let staging = staging_dir(target_dir);
+            if staging.exists() {
+                let _ = helpers::remove_entry(&staging);
+            }
+            return match recovery {
+                Ok(_) => Err(format!("{}. Previous profiles have been restored.", e)),
+                Err(rollback_error) => Err(format!(
+                    "{}. Restoring previous profiles also failed: {}",
+                    e, rollback_error
+                )),
+            };
+        }
+    };
+
+    // The previous entries are no longer needed. The swap is committed, so a failure
+    // here must not fail it; whatever is left is cleared before the next one.
+    remove_leftovers(target_dir).unwrap_or_else(|e| println!("{}", e));
+
+    Ok(removed)
+}
+
+// Swap the staged links in, record them in the manifest and return the previous entries
+// that were moved aside
+fn swap_in_staged_links(
+    target_dir: &Path,
+    displaced: &[PathBuf],
+    manifest: &ManagedManifest,
+) -> Result<Vec<String>, String> {
+    let staging = staging_dir(target_dir);
+    let entries_dir = rollback_entries_dir(target_dir);
+
+    let mut installed = Vec::new();
+    for entry in
+        fs::read_dir(&staging).map_err(|e| format!("Failed to read staging directory: {}", e))?
+    {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        installed.push(entry.file_name().to_string_lossy().to_string());
+    }
+
+    // Directories are not moved aside, so make sure none of them blocks a staged link
+    // before anything in the target directory is touched
+    for name in &installed {
+        let target_path = target_dir.join(name);
+        if target_path.is_dir() && !target_path.is_symlink() && !displaced.contains(&target_path) {
+            return Err(format!(
+                "Cannot activate {:?}: a folder with the same name already exists",
+                target_path
+            ));
+        }
+    }
+
+    fs::create_dir_all(&entries_dir)
+        .map_err(|e| format!("Failed to create rollback directory: {}", e))?;
+
+    // Keep the previous manifest so a rollback restores ownership along with the entries
+    let manifest_path = managed_manifest::manifest_path(target_dir);
+    let had_manifest = manifest_path.exists();
+    if had_manifest {
+        fs::copy(&manifest_path, manifest_backup_path(target_dir))
+            .map_err(|e| format!("Failed to back up manifest: {}", e))?;
+    }
+
+    let mut journal = ActivationJournal {
+        phase: JournalPhase::Displacing,
+        installed,
+        had_manifest,
+    };
+    write_journal(target_dir, &journal)?;
+
+    // Move the entries being removed or retargeted into the rollback folder
+    let mut removed = Vec::new();
+    for path in displaced {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| format!("Failed to get file name from {:?}", path))?;
+
+        println!("Moving previous entry aside: {:?}", path);
+        fs::rename(path, entries_dir.join(file_name))
+            .map_err(|e| format!("Failed to move {:?} aside: {}", path, e))?;
+        removed.push(path.to_string_lossy().to_string());
+    }
+
+    journal.phase = JournalPhase::Installing;
+    write_journal(target_dir, &journal)?;
+
+    for name in &journal.installed {
+        let target_path = target_dir.join(name);
+        fs::rename(staging.join(name), &target_path)
+            .map_err(|e| format!("Failed to move link into place {:?}: {}", target_path, e))?;
+    }
+
+    managed_manifest::save_manifest(target_dir, manifest)?;
+
+    Ok(removed)
+}
+
+// The commit point: once the journal is gone, recover_activation no longer rolls the
+// activation back
+fn commit(target_dir: &Path) -> Result<(), String> {
+    fs::remove_file(journal_path(target_dir))
+        .map_err(|e| format!("Failed to commit activation: {}", e))
+}
+
+// Remove the rollback and staging folders of a finished activation
+fn remove_leftovers(target_dir: &Path) -> Result<(), String> {
+    for dir in [rollback_dir(target_dir), staging_dir(target_dir)] {
+        if dir.exists() {
+            helpers::remove_entry(&dir)
+                .map_err(|e| format!("Failed to clean up {:?}: {}", dir, e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Restore the state from before an interrupted activation, if a journal is present.
+/// Returns true if a rollback was performed.
+pub fn recover_activation(target_dir: &Path) -> Result<bool, String> {
+    let journal = match read_journal(target_dir)? {
+        Some(journal) => journal,
+        None => {
+            // A committed activation may have left its folders behind. They must be gone
+            // before the next one, or its rollback would bring back their old entries.
+            remove_leftovers(target_dir)?;
+            return Ok(false);
+        }
+    };
+
+    println!(
+        "Found unfinished activation journal in {:?}, rolling back",
+        target_dir
+    );
+
+    // Only once every previous entry is moved aside can the installed names be removed safely
+    if journal.phase == JournalPhase::Installing {
+        for name in &journal.installed {
+            let path = target_dir.join(name);
+            if path.exists() || path.is_symlink() {
+                helpers::remove_entry(&path)?;
+            }
+        }
+    }
+
+    let entries_dir = rollback_entries_dir(target_dir);
+    if entries_dir.exists() {
+        let entries = fs::read_dir(&entries_dir)
+            .map_err(|e| format!("Failed to read rollback directory: {}", e))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let original_path = target_dir.join(entry.file_name());
+
+            println!("Restoring previous entry: {:?}", original_path);
+            fs::rename(entry.path(), &original_path)
+                .map_err(|e| format!("Failed to restore {:?}: {}", original_path, e))?;
+        }
+    }
+
+    let manifest_path = managed_manifest::manifest_path(target_dir);
+    if journal.had_manifest {
+        fs::copy(manifest_backup_path(target_dir), &manifest_path)
+            .map_err(|e| format!("Failed to restore manifest: {}", e))?;
+    } else if manifest_path.exists() {
+        helpers::remove_entry(&manifest_path)?;
+    }
+
+    remove_leftovers(target_dir)?;
+
+    Ok(true)
+}
+
+// Called on startup to roll back an activation that was interrupted by a crash or power loss
+pub fn recover_interrupted_activation(app: &AppHandle) {
+    let target_dir = match get_gsx_target_dir(app) {
+        Ok(dir) => dir,
+        Err(e) => {
+            println!("Skipping activation recovery: {}", e);
+            return;
+        }
+    };
+
+    match recover_activation(&target_dir) {
+        Ok(true) => println!("Recovered GSX profiles from an interrupted activation"),
+        Ok(false) => {}
+        Err(e) => println!("Failed to recover interrupted activation: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activate(library: &Path, target: &Path, files: &[&str]) -> LinkOutcome {
+        let file_paths = files
+            .iter()
+            .map(|file| library.join(file).to_string_lossy().to_string())
+            .collect();
+
+        activate_transactionally(
+            file_paths,
+            target,
+            LinkStrategy::Auto,
+            UnmanagedPolicy::default(),
+            &ProgressReporter::default(),
+        )
+        .unwrap()
+    }
+
+    fn setup() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("gsx-profiles");
+        let target = dir.path().join("GSX");
+        fs::create_dir_all(&library).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        for name in ["eddm-aerosoft.ini", "egll-justsim.ini"] {
+            fs::write(library.join(name), name).unwrap();
+        }
+
+        (dir, library, target)
+    }
+
+    #[test]
+    fn committed_activation_leaves_nothing_to_roll_back() {
+        let (_dir, library, target) = setup();
+
+        activate(&library, &target, &["eddm-aerosoft.ini"]);
+        let outcome = activate(&library, &target, &["egll-justsim.ini"]);
+
+        assert_eq!(outcome.removed.len(), 1);
+        assert!(!journal_path(&target).exists());
+        assert!(!rollback_dir(&target).exists());
+        assert!(!staging_dir(&target).exists());
+        assert!(!recover_activation(&target).unwrap());
+        assert!(target.join("egll-justsim.ini").exists());
+    }
+
+    #[test]
+    fn leftovers_of_a_committed_activation_are_cleared_not_restored() {
+        let (_dir, library, target) = setup();
+        activate(&library, &target, &["egll-justsim.ini"]);
+
+        // Cleanup failed after the commit: the old entry is still in the rollback folder
+        fs::create_dir_all(rollback_entries_dir(&target)).unwrap();
+        fs::write(
+            rollback_entries_dir(&target).join("eddm-aerosoft.ini"),
+            "old",
+        )
+        .unwrap();
+        fs::create_dir_all(staging_dir(&target)).unwrap();
+
+        assert!(!recover_activation(&target).unwrap());
+
+        assert!(!rollback_dir(&target).exists());
+        assert!(!staging_dir(&target).exists());
+        assert!(!target.join("eddm-aerosoft.ini").exists());
+        assert!(target.join("egll-justsim.ini").exists());
+    }
+
+    #[test]
+    fn interrupted_activation_is_rolled_back() {
+        let (_dir, library, target) = setup();
+        activate(&library, &target, &["eddm-aerosoft.ini"]);
+        let manifest = managed_manifest::load_manifest(&target).unwrap();
+
+        // The app died after moving the old link aside, before the commit
+        fs::create_dir_all(rollback_entries_dir(&target)).unwrap();
+        fs::copy(
+            managed_manifest::manifest_path(&target),
+            manifest_backup_path(&target),
+        )
+        .unwrap();
+        write_journal(
+            &target,
+            &ActivationJournal {
+                phase: JournalPhase::Installing,
+                installed: vec!["egll-justsim.ini".to_string()],
+                had_manifest: true,
+            },
+        )
+        .unwrap();
+        fs::rename(
+            target.join("eddm-aerosoft.ini"),
+            rollback_entries_dir(&target).join("eddm-aerosoft.ini"),
+        )
+        .unwrap();
+        fs::write(target.join("egll-justsim.ini"), "new").unwrap();
+
+        assert!(recover_activation(&target).unwrap());
+
+        assert!(target.join("eddm-aerosoft.ini").exists());
+        assert!(!target.join("egll-justsim.ini").exists());
+        assert_eq!(managed_manifest::load_manifest(&target).unwrap(), manifest);
+        assert!(!rollback_dir(&target).exists());
+    }
+
+    #[test]
+    fn journal_cut_off_while_written_does_not_block_activations() {
+        let (_dir, library, target) = setup();
+        activate(&library, &target, &["eddm-aerosoft.ini"]);
+
+        // The app died while writing the first journal, before anything was moved
+        fs::create_dir_all(rollback_dir(&target)).unwrap();
+        fs::write(
+            journal_path(&target).with_extension("partial"),
+            "{\"phase\": \"displ",
+        )
+        .unwrap();
+
+        assert!(!recover_activation(&target).unwrap());
+        assert!(!rollback_dir(&target).exists());
+
+        activate(&library, &target, &["egll-justsim.ini"]);
+        assert!(target.join("egll-justsim.ini").exists());
+        assert!(!target.join("eddm-aerosoft.ini").exists());
+    }
+}
@@ -1,24 +1,105 @@
 use regex::Regex;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
-use tauri::command;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{command, AppHandle};
 use walkdir::WalkDir;
 
+use crate::bgl_reader;
+use crate::msfs_usercfg;
+use crate::package_manifest::{self, PackageManifest};
+use crate::settings;
+
 #[derive(Debug, Serialize, Clone)]
 pub struct AirportInfo {
     pub icao: String,
     pub title: String,
-    pub path: String,
+    pub path: String, // Real location, with any links along the way resolved
+    pub link_path: Option<String>, // Where the scan found it, if that was through a link
     pub folder_type: String, // Added to track which folder type (Community or StreamedPackages)
+    pub label: String, // The scan root's label, or the folder type for MSFS folders
+    pub developer: String,
+    pub fsversion: String,
+    pub name: String,
+    pub package_version: Option<String>, // From manifest.json, if the package has one
+    pub minimum_game_version: Option<String>,
+    pub package_icaos: Vec<String>, // Every airport of the package this one belongs to
+}
+
+/// A scenery package with the airports it contains
+#[derive(Debug, Serialize, Clone)]
+pub struct AirportPackage {
+    pub title: String,
+    pub path: String,
+    pub link_path: Option<String>,
+    pub folder_type: String,
+    pub label: String,
+    pub icaos: Vec<String>, // Upper case, in the order found; most packages have one
     pub developer: String,
     pub fsversion: String,
     pub name: String,
+    pub package_version: Option<String>,
+    pub minimum_game_version: Option<String>,
+}
+
+/// An extra folder to scan for airports, e.g. the library of an add-on linker
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScanRoot {
+    pub path: String,
+    pub label: String,
+    pub folder_type: String, // Reported as AirportInfo.folder_type, e.g. "Community"
+}
+
+// Folder types holding the sim's own packages
+const OFFICIAL_FOLDER_TYPES: [&str; 2] = ["Official", "StreamedPackages"];
+
+// A folder to scan with its folder type and label
+struct ScanFolder {
+    path: PathBuf,
+    folder_type: String,
+    label: String,
+}
+
+// canonicalize returns \\?\ paths on Windows, which the rest of the app never shows
+fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+    let text = path.to_string_lossy().to_string();
+    if let Some(rest) = text.strip_prefix(r"\\?\UNC\") {
+        return PathBuf::from(format!(r"\\{}", rest));
+    }
+    match text.strip_prefix(r"\\?\") {
+        Some(rest) => PathBuf::from(rest),
+        None => path,
+    }
+}
+
+/// Resolve symlinks and junctions on the way to `path`, so an add-on linked into Community
+/// is reported at its library location. Returns None when nothing was linked.
+pub fn resolve_link_target(path: &Path) -> Option<PathBuf> {
+    // Junctions count as symlinks here too
+    if !path.ancestors().any(|ancestor| ancestor.is_symlink()) {
+        return None;
+    }
+
+    fs::canonicalize(path).ok().map(strip_verbatim_prefix)
 }
 
 fn find_msfs_community_folders() -> Vec<(PathBuf, String)> {
     let mut community_folders = Vec::new();
 
+    // Use the packages folder each installation was set up with, which is often on another
+    // drive than the defaults below
+    for install in msfs_usercfg::find_installs(&msfs_usercfg::user_cfg_candidates()) {
+        println!(
+            "Found InstalledPackagesPath {} in {}",
+            install.installed_packages_path.display(),
+            install.user_cfg.display()
+        );
+        community_folders.extend(msfs_usercfg::package_folders(
+            &install.installed_packages_path,
+        ));
+    }
+
     // Check MSFS 2020 locations
     if let Some(local_app_data) = dirs_next::data_local_dir() {
         let store_path_2020 = local_app_data
@@ -83,22 +164,51 @@ fn find_msfs_community_folders() -> Vec<(PathBuf, String)> {
         }
     }
 
+    // A default location is usually also the one in UserCfg.opt, so only scan it once
+    let mut seen_folders = HashSet::new();
+    community_folders.retain(|(folder, _)| seen_folders.insert(folder.clone()));
+
     community_folders
 }
 
-#[command]
-pub fn scan_for_airport_scenery() -> Result<Vec<AirportInfo>, String> {
-    // We only need the regex to extract ICAO codes after "-airport-"
-    let airport_icao_regex =
-        Regex::new(r"(?i)-airport-([A-Z0-9]{4})-").map_err(|e| e.to_string())?;
+// The registered scan roots that exist, after the MSFS folders
+fn find_scan_folders(scan_roots: &[ScanRoot]) -> Vec<ScanFolder> {
+    let mut folders: Vec<ScanFolder> = find_msfs_community_folders()
+        .into_iter()
+        .map(|(path, folder_type)| ScanFolder {
+            path,
+            label: folder_type.clone(),
+            folder_type,
+        })
+        .collect();
+
+    for root in scan_roots {
+        let path = PathBuf::from(&root.path);
+        if !path.is_dir() {
+            println!("Skipping missing scan root: {} ({})", root.path, root.label);
+            continue;
+        }
 
-    let mut airports = Vec::new();
+        if folders.iter().all(|folder| folder.path != path) {
+            folders.push(ScanFolder {
+                path,
+                folder_type: root.folder_type.clone(),
+                label: root.label.clone(),
+            });
+        }
+    }
 
-    // Track seen ICAOs per folder and folder type to ensure we only show each ICAO
-    // once per Community folder and once per StreamedPackages folder
-    let mut seen_airports: HashMap<String, HashSet<String>> = HashMap::new();
+    folders
+}
+
+// Walk the MSFS folders and scan roots for scenery packages that contain airports
+fn scan_packages(
+    scan_roots: &[ScanRoot],
+    read_official_scenery: bool,
+) -> Result<Vec<AirportPackage>, String> {
+    let mut packages = Vec::new();
 
-    let community_folders = find_msfs_community_folders();
+    let community_folders = find_scan_folders(scan_roots);
     if community_folders.is_empty() {
         return Err("No MSFS Community folders found".to_string());
     }
@@ -107,84 +217,322 @@ pub fn scan_for_airport_scenery() -> Result<Vec<AirportInfo>, String> {
         "Found {} community folders to scan",
         community_folders.len()
     );
-    for (folder, folder_type) in &community_folders {
-        println!("Found folder: {} ({})", folder.display(), folder_type);
+    for folder in &community_folders {
+        println!("Found folder: {} ({})", folder.path.display(), folder.label);
     }
 
-    for (folder, folder_type) in community_folders {
-        // Create a new set for tracking seen ICAOs in this specific folder
-        let folder_seen_icaos = seen_airports
-            .entry(folder_type.clone())
-            .or_insert_with(HashSet::new);
-
-        println!("Scanning folder: {} ({})", folder.display(), folder_type);
-
-        // Walk through the directory with limited depth
-        for entry in WalkDir::new(&folder)
-            .follow_links(true)
-            .max_depth(3)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-
-            // Skip if not a directory
-            if !path.is_dir() {
-                continue;
+    for folder in &community_folders {
+        packages.extend(scan_folder(folder, read_official_scenery));
+    }
+
+    Ok(packages)
+}
+
+// The airport packages in one scan folder. The scenery of the sim's own packages is only
+// read when asked, as they add up to gigabytes of BGL files; their folder names mostly name
+// the airport anyway.
+fn scan_folder(scan_folder: &ScanFolder, read_official_scenery: bool) -> Vec<AirportPackage> {
+    let ScanFolder {
+        path: folder,
+        folder_type,
+        label,
+    } = scan_folder;
+    let read_scenery =
+        read_official_scenery || !OFFICIAL_FOLDER_TYPES.contains(&folder_type.as_str());
+    let mut packages = Vec::new();
+
+    println!("Scanning folder: {} ({})", folder.display(), folder_type);
+
+    // Walk through the directory with limited depth
+    let mut walker = WalkDir::new(folder)
+        .follow_links(true)
+        .max_depth(3)
+        .into_iter();
+
+    while let Some(entry) = walker.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        let path = entry.path();
+
+        // Skip if not a directory
+        if !path.is_dir() {
+            continue;
+        }
+
+        let original_folder_name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+
+        let manifest = match package_manifest::read_package_manifest(path) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                println!("Ignoring manifest of {}: {}", path.display(), e);
+                None
             }
+        };
 
-            let folder_name = match path.file_name().and_then(|n| n.to_str()) {
-                Some(name) => name.to_uppercase(), // Convert to uppercase for case-insensitive matching
-                None => continue,
-            };
-
-            // Check if folder name contains "-airport-" and extract ICAO directly after it
-            if let Some(cap) = airport_icao_regex.captures(&folder_name) {
-                if let Some(icao_match) = cap.get(1) {
-                    let icao = icao_match.as_str();
-
-                    // Add the airport if we haven't seen this ICAO in this folder type before
-                    if folder_seen_icaos.insert(icao.to_string()) {
-                        let original_folder_name = path
-                            .file_name()
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .to_string();
-
-                        // Extract developer and FS version based on folder type
-                        let (developer, fsversion) =
-                            extract_developer_and_version(&original_folder_name, &folder_type);
-
-                        // Extract the airport name
-                        let airport_name = extract_airport_name(&original_folder_name, icao);
-
-                        // Format the title with the folder type
-                        let title = format!("{} ({})", original_folder_name, folder_type);
-
-                        airports.push(AirportInfo {
-                            icao: icao.to_string(),
-                            title,
-                            path: path.to_string_lossy().to_string(),
-                            folder_type: folder_type.clone(),
-                            developer,
-                            fsversion: fsversion.clone(),
-                            name: airport_name.clone(),
-                        });
-
-                        println!(
-                            "Found airport: {} ({}) in folder {} ({}) version {}",
-                            icao, airport_name, folder_name, folder_type, fsversion
-                        );
-                    }
+        let folder_icaos = package_manifest::airport_folder_icaos(&original_folder_name);
+
+        let mut package = match &manifest {
+            // A package's own subfolders are never packages
+            Some(manifest) => {
+                walker.skip_current_dir();
+                if !manifest.is_scenery() {
+                    continue;
+                }
+                package_from_manifest(
+                    package_icaos(
+                        path,
+                        folder_icaos,
+                        &original_folder_name,
+                        manifest,
+                        read_scenery,
+                    ),
+                    manifest,
+                    &original_folder_name,
+                    folder_type,
+                )
+            }
+            // Without a manifest only an explicit "-airport-xxxx-" folder name counts
+            None => package_from_folder_name(folder_icaos, &original_folder_name, folder_type),
+        };
+
+        if package.icaos.is_empty() {
+            continue;
+        }
+
+        // Add-on linkers link their library into Community; report the library
+        let (real_path, link_path) = match resolve_link_target(path) {
+            Some(real_path) => (real_path, Some(path.to_string_lossy().to_string())),
+            None => (path.to_path_buf(), None),
+        };
+
+        // Format the title with the folder type, or the scan root's label
+        package.title = format!("{} ({})", original_folder_name, label);
+        package.path = real_path.to_string_lossy().to_string();
+        package.link_path = link_path;
+        package.label = label.clone();
+
+        println!(
+            "Found package: {} with {} in folder {} ({}) version {}",
+            original_folder_name,
+            package.icaos.join(", "),
+            folder.display(),
+            folder_type,
+            package.fsversion
+        );
+
+        packages.push(package);
+    }
+
+    packages
+}
+
+// Every airport named in the package's folder name or found in its scenery. Only when its
+// scenery can't be read, or isn't, is a guess from its names used.
+fn package_icaos(
+    package_dir: &Path,
+    mut icaos: Vec<String>,
+    folder_name: &str,
+    manifest: &PackageManifest,
+    read_scenery: bool,
+) -> Vec<String> {
+    let scenery_icaos = if read_scenery {
+        bgl_reader::package_airport_idents(package_dir).unwrap_or_else(|e| {
+            println!("Failed to read scenery of {}: {}", package_dir.display(), e);
+            None
+        })
+    } else {
+        None
+    };
+
+    match scenery_icaos {
+        Some(scenery_icaos) => {
+            for icao in scenery_icaos {
+                if !icaos.contains(&icao) {
+                    icaos.push(icao);
                 }
             }
         }
+        None if icaos.is_empty() => {
+            icaos.extend(package_manifest::guess_icao(folder_name, manifest));
+        }
+        None => {}
     }
 
+    icaos
+}
+
+/// One entry per airport, so each airport of a package can be matched against profiles
+pub fn airports_from_packages(packages: &[AirportPackage]) -> Vec<AirportInfo> {
+    let mut airports = Vec::new();
+
+    // Track seen ICAOs per folder type to ensure we only show each ICAO
+    // once per Community folder and once per StreamedPackages folder
+    let mut seen_airports: HashMap<String, HashSet<String>> = HashMap::new();
+
+    for package in packages {
+        let folder_seen_icaos = seen_airports
+            .entry(package.folder_type.clone())
+            .or_default();
+
+        for icao in &package.icaos {
+            // Add the airport if we haven't seen this ICAO in this folder type before
+            if !folder_seen_icaos.insert(icao.clone()) {
+                continue;
+            }
+
+            airports.push(AirportInfo {
+                icao: icao.clone(),
+                title: package.title.clone(),
+                path: package.path.clone(),
+                link_path: package.link_path.clone(),
+                folder_type: package.folder_type.clone(),
+                label: package.label.clone(),
+                developer: package.developer.clone(),
+                fsversion: package.fsversion.clone(),
+                name: package.name.clone(),
+                package_version: package.package_version.clone(),
+                minimum_game_version: package.minimum_game_version.clone(),
+                package_icaos: package.icaos.clone(),
+            });
+        }
+    }
+
+    airports
+}
+
+#[command]
+pub fn scan_for_airport_scenery(app: AppHandle) -> Result<Vec<AirportInfo>, String> {
+    let settings = settings::load_settings(&app)?;
+    let airports = airports_from_packages(&scan_packages(
+        &settings.scan_roots,
+        settings.read_official_scenery,
+    )?);
+
     println!("Found {} airports in total", airports.len());
     Ok(airports)
 }
 
+/// The scenery packages and the airports each one contains
+#[command]
+pub fn scan_airport_packages(app: AppHandle) -> Result<Vec<AirportPackage>, String> {
+    let settings = settings::load_settings(&app)?;
+    let packages = scan_packages(&settings.scan_roots, settings.read_official_scenery)?;
+
+    println!("Found {} airport packages in total", packages.len());
+    Ok(packages)
+}
+
+#[command]
+pub fn list_scan_roots(app: AppHandle) -> Result<Vec<ScanRoot>, String> {
+    Ok(settings::load_settings(&app)?.scan_roots)
+}
+
+/// Register a folder to scan, or update the label and type of one already registered
+#[command]
+pub fn add_scan_root(app: AppHandle, root: ScanRoot) -> Result<Vec<ScanRoot>, String> {
+    if !Path::new(&root.path).is_dir() {
+        return Err(format!("Scan root is not a folder: {}", root.path));
+    }
+    if root.label.trim().is_empty() {
+        return Err("Scan root needs a label".to_string());
+    }
+    if root.folder_type.trim().is_empty() {
+        return Err("Scan root needs a folder type".to_string());
+    }
+
+    let mut settings = settings::load_settings(&app)?;
+    match settings
+        .scan_roots
+        .iter_mut()
+        .find(|existing| Path::new(&existing.path) == Path::new(&root.path))
+    {
+        Some(existing) => *existing = root,
+        None => settings.scan_roots.push(root),
+    }
+
+    settings::save_settings(&app, &settings)?;
+    Ok(settings.scan_roots)
+}
+
+#[command]
+pub fn remove_scan_root(app: AppHandle, path: String) -> Result<Vec<ScanRoot>, String> {
+    let mut settings = settings::load_settings(&app)?;
+    let count = settings.scan_roots.len();
+
+    settings
+        .scan_roots
+        .retain(|root| Path::new(&root.path) != Path::new(&path));
+    if settings.scan_roots.len() == count {
+        return Err(format!("Scan root not found: {}", path));
+    }
+
+    settings::save_settings(&app, &settings)?;
+    Ok(settings.scan_roots)
+}
+
+// Creator and title come from the manifest; the FS version prefix only exists in the name.
+// Where the package was found is filled in by the scan.
+fn package_from_manifest(
+    icaos: Vec<String>,
+    manifest: &PackageManifest,
+    folder_name: &str,
+    folder_type: &str,
+) -> AirportPackage {
+    let (folder_developer, fsversion) = extract_developer_and_version(folder_name, folder_type);
+
+    let non_empty = |value: &str| Some(value.trim().to_string()).filter(|value| !value.is_empty());
+
+    AirportPackage {
+        developer: non_empty(&manifest.creator).unwrap_or(folder_developer),
+        name: non_empty(&manifest.title).unwrap_or_else(|| {
+            extract_airport_name(folder_name, icaos.first().map_or("", String::as_str))
+        }),
+        package_version: non_empty(&manifest.package_version),
+        minimum_game_version: non_empty(&manifest.minimum_game_version),
+        fsversion,
+        icaos,
+        ..new_package(folder_type)
+    }
+}
+
+// Packages without a manifest.json are described by their folder name alone
+fn package_from_folder_name(
+    icaos: Vec<String>,
+    folder_name: &str,
+    folder_type: &str,
+) -> AirportPackage {
+    let (developer, fsversion) = extract_developer_and_version(folder_name, folder_type);
+
+    AirportPackage {
+        name: extract_airport_name(folder_name, icaos.first().map_or("", String::as_str)),
+        developer,
+        fsversion,
+        icaos,
+        ..new_package(folder_type)
+    }
+}
+
+fn new_package(folder_type: &str) -> AirportPackage {
+    AirportPackage {
+        title: String::new(),
+        path: String::new(),
+        link_path: None,
+        folder_type: folder_type.to_string(),
+        label: String::new(),
+        icaos: Vec::new(),
+        developer: String::new(),
+        fsversion: String::new(),
+        name: String::new(),
+        package_version: None,
+        minimum_game_version: None,
+    }
+}
+
 /// Extract the developer and flight simulator version from the folder name
 /// For streamed packages: First part is FS version (fs24-xxx, fs20-xxx), second part is developer
 /// For community packages: First part is always the developer, no FS version specified
@@ -252,3 +600,125 @@ fn extract_airport_name(folder_name: &str, icao: &str) -> String {
 
     "Unknown".to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bgl_fixture(name: &str) -> Vec<u8> {
+        fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures/bgl")
+                .join(name),
+        )
+        .unwrap()
+    }
+
+    // A scenery package for LEPA and LESJ whose folder name names neither
+    fn write_package(folder: &Path, name: &str) {
+        let package = folder.join(name);
+        fs::create_dir_all(package.join("scenery")).unwrap();
+        fs::write(
+            package.join("manifest.json"),
+            r#"{"content_type": "SCENERY", "title": "Mallorca", "creator": "Asobo"}"#,
+        )
+        .unwrap();
+        fs::write(
+            package.join("layout.json"),
+            r#"{"content": [{"path": "scenery/airports.bgl"}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            package.join("scenery/airports.bgl"),
+            bgl_fixture("airports.bgl"),
+        )
+        .unwrap();
+    }
+
+    fn scan_folder_of_type(path: &Path, folder_type: &str) -> ScanFolder {
+        ScanFolder {
+            path: path.to_path_buf(),
+            folder_type: folder_type.to_string(),
+            label: folder_type.to_string(),
+        }
+    }
+
+    #[test]
+    fn reads_community_scenery_for_every_airport() {
+        let dir = tempfile::tempdir().unwrap();
+        write_package(dir.path(), "asobo-scenery-mallorca");
+
+        let packages = scan_folder(&scan_folder_of_type(dir.path(), "Community"), false);
+
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].icaos, vec!["LEPA", "LESJ"]);
+    }
+
+    #[test]
+    fn reads_official_scenery_only_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        write_package(dir.path(), "asobo-scenery-mallorca");
+        write_package(dir.path(), "asobo-airport-lemh-menorca");
+        let official = scan_folder_of_type(dir.path(), "Official");
+
+        // Only the folder name is used, so the unnamed package is left out
+        let packages = scan_folder(&official, false);
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].icaos, vec!["LEMH"]);
+
+        let packages = scan_folder(&official, true);
+        assert_eq!(packages.len(), 2);
+        assert!(packages
+            .iter()
+            .any(|package| package.icaos == ["LEPA", "LESJ"]));
+        assert!(packages
+            .iter()
+            .any(|package| package.icaos == ["LEMH", "LEPA", "LESJ"]));
+    }
+
+    fn package(folder_type: &str, icaos: &[&str]) -> AirportPackage {
+        AirportPackage {
+            title: format!("{} ({})", icaos.join("-"), folder_type),
+            path: format!("/{}/{}", folder_type, icaos.join("-")),
+            link_path: None,
+            folder_type: folder_type.to_string(),
+            label: folder_type.to_string(),
+            icaos: icaos.iter().map(|icao| icao.to_string()).collect(),
+            developer: "asobo".to_string(),
+            fsversion: "MSFS 2020".to_string(),
+            name: icaos.join("-"),
+            package_version: None,
+            minimum_game_version: None,
+        }
+    }
+
+    #[test]
+    fn lists_every_airport_of_a_multi_airport_package() {
+        let packages = [
+            package("Community", &["LEPA", "LESJ", "LEIB"]),
+            // Already listed for Community, but not for StreamedPackages
+            package("Community", &["LEIB"]),
+            package("StreamedPackages", &["LEIB"]),
+        ];
+
+        let airports = airports_from_packages(&packages);
+
+        let listed: Vec<(&str, &str)> = airports
+            .iter()
+            .map(|airport| (airport.icao.as_str(), airport.folder_type.as_str()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("LEPA", "Community"),
+                ("LESJ", "Community"),
+                ("LEIB", "Community"),
+                ("LEIB", "StreamedPackages"),
+            ]
+        );
+        for airport in &airports[..3] {
+            assert_eq!(airport.path, packages[0].path);
+            assert_eq!(airport.package_icaos, vec!["LEPA", "LESJ", "LEIB"]);
+        }
+    }
+}
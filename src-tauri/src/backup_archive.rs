@@ -0,0 +1,319 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::read::ZipArchive;
+use zip::write::{SimpleFileOptions, ZipWriter};
+use zip::CompressionMethod;
+
+use crate::activation_progress::{ActivationProgress, ProgressReporter};
+use crate::backup_store::sha256_hex;
+use crate::create_profile_symlink::helpers;
+use crate::managed_manifest::{self, ManagedEntry};
+use crate::profile_backups::{self, BackupFormat};
+
+// Inside an archive, the backed up files live under FILES_FOLDER next to the manifest
+const MANIFEST_ENTRY_NAME: &str = "manifest.json";
+const FILES_FOLDER: &str = "files";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchivedFile {
+    pub name: String,
+    pub original_path: String,
+    pub size_bytes: u64,
+    pub sha256: String, // Lower case hex
+}
+
+/// Stored as manifest.json in every backup archive
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct BackupArchiveManifest {
+    pub created_at: u64, // Unix seconds
+    pub target_dir: String,
+    pub files: Vec<ArchivedFile>,
+    pub active_profile_ids: Vec<String>, // Profiles the frontend had applied at the time
+    pub active_links: Vec<ManagedEntry>, // Links the manager had activated at the time
+}
+
+/// Write the real files in the GSX folder to `<archive_dir>/backup-<timestamp>.zip` and
+/// return the archive path. Links are not archived, only listed in the manifest. If nothing
+/// changed since the newest archive of this folder, that one is returned instead.
+pub fn create_backup_archive(
+    target_dir: &Path,
+    archive_dir: &Path,
+    active_profile_ids: &[String],
+    progress: &ProgressReporter,
+) -> Result<PathBuf, String> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| format!("Failed to get system time: {}", e))?
+        .as_secs();
+
+    // Archives in the GSX folder would be picked up as profile files
+    if archive_dir.starts_with(target_dir) {
+        return Err("The backup location must be outside the GSX profile folder".to_string());
+    }
+
+    let files = read_real_files(target_dir)?;
+
+    // An archive identical to the previous one would only add clutter
+    if let Some(latest) = unchanged_archive(target_dir, archive_dir, &files) {
+        println!("Skipping backup, nothing changed since {:?}", latest);
+        return Ok(latest);
+    }
+
+    fs::create_dir_all(archive_dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    let archive_path = archive_dir.join(format!("backup-{}.zip", timestamp));
+    // Written under a temporary name so a cancelled or failed backup never looks complete
+    let partial_path = archive_dir.join(format!("backup-{}.zip.partial", timestamp));
+
+    println!("Creating backup archive: {:?}", archive_path);
+
+    let result = write_archive(
+        &partial_path,
+        target_dir,
+        timestamp,
+        active_profile_ids,
+        files,
+        progress,
+    )
+    .and_then(|_| {
+        fs::rename(&partial_path, &archive_path)
+            .map_err(|e| format!("Failed to finish backup archive: {}", e))
+    });
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial_path);
+        return Err(e);
+    }
+
+    Ok(archive_path)
+}
+
+// The real files in the GSX folder with their hashes, sorted by name. Same selection as
+// create_profile_backup: real files, including those in subfolders.
+fn read_real_files(target_dir: &Path) -> Result<Vec<(ArchivedFile, Vec<u8>)>, String> {
+    let mut files = Vec::new();
+
+    for path in helpers::collect_real_files(target_dir)? {
+        let content = fs::read(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+
+        files.push((
+            ArchivedFile {
+                name: helpers::relative_name(target_dir, &path),
+                original_path: path.to_string_lossy().to_string(),
+                size_bytes: content.len() as u64,
+                sha256: sha256_hex(&content),
+            },
+            content,
+        ));
+    }
+
+    files.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+    Ok(files)
+}
+
+// The newest archive of this GSX folder, if it holds exactly these files. The archives of
+// other GSX folders in the same backup location are passed over.
+fn unchanged_archive(
+    target_dir: &Path,
+    archive_dir: &Path,
+    files: &[(ArchivedFile, Vec<u8>)],
+) -> Option<PathBuf> {
+    let backups = match profile_backups::list_backups(target_dir, Some(archive_dir)) {
+        Ok(backups) => backups,
+        Err(e) => {
+            println!("Failed to compare with previous backups: {}", e);
+            return None;
+        }
+    };
+
+    let current: Vec<(&str, &str)> = files
+        .iter()
+        .map(|(file, _)| (file.name.as_str(), file.sha256.as_str()))
+        .collect();
+
+    // list_backups only returns the archives of this GSX folder, newest first
+    let latest = backups
+        .iter()
+        .find(|backup| backup.format == BackupFormat::Zip)?;
+    let path = PathBuf::from(&latest.path);
+    let manifest = read_archive_manifest(&path).ok()?;
+
+    let mut archived: Vec<(&str, &str)> = manifest
+        .files
+        .iter()
+        .map(|file| (file.name.as_str(), file.sha256.as_str()))
+        .collect();
+    archived.sort();
+
+    (archived == current).then_some(path)
+}
+
+fn write_archive(
+    archive_path: &Path,
+    target_dir: &Path,
+    timestamp: u64,
+    active_profile_ids: &[String],
+    files: Vec<(ArchivedFile, Vec<u8>)>,
+    progress: &ProgressReporter,
+) -> Result<(), String> {
+    let file = File::create(archive_path)
+        .map_err(|e| format!("Failed to create backup archive: {}", e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut manifest = BackupArchiveManifest {
+        created_at: timestamp,
+        target_dir: target_dir.to_string_lossy().to_string(),
+        active_profile_ids: active_profile_ids.to_vec(),
+        active_links: managed_manifest::load_or_infer_manifest(target_dir)?.entries,
+        ..Default::default()
+    };
+
+    let total = files.len();
+    for (index, (file, content)) in files.into_iter().enumerate() {
+        progress.check_cancelled()?;
+
+        zip.start_file(format!("{}/{}", FILES_FOLDER, file.name), options)
+            .map_err(|e| format!("Failed to add {} to backup archive: {}", file.name, e))?;
+        zip.write_all(&content)
+            .map_err(|e| format!("Failed to write {} to backup archive: {}", file.name, e))?;
+
+        progress.report(ActivationProgress::BackupCopied {
+            current: index + 1,
+            total,
+            file: file.original_path.clone(),
+        });
+        manifest.files.push(file);
+    }
+
+    let manifest_json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize backup manifest: {}", e))?;
+    zip.start_file(MANIFEST_ENTRY_NAME, options)
+        .map_err(|e| format!("Failed to add manifest to backup archive: {}", e))?;
+    zip.write_all(manifest_json.as_bytes())
+        .map_err(|e| format!("Failed to write manifest to backup archive: {}", e))?;
+
+    zip.finish()
+        .map_err(|e| format!("Failed to finish backup archive: {}", e))?;
+
+    Ok(())
+}
+
+fn open_archive(archive_path: &Path) -> Result<ZipArchive<File>, String> {
+    let file =
+        File::open(archive_path).map_err(|e| format!("Failed to open backup archive: {}", e))?;
+    ZipArchive::new(file).map_err(|e| format!("Failed to read backup archive: {}", e))
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| format!("Failed to find {} in backup archive: {}", name, e))?;
+
+    let mut content = Vec::new();
+    entry
+        .read_to_end(&mut content)
+        .map_err(|e| format!("Failed to read {} from backup archive: {}", name, e))?;
+
+    Ok(content)
+}
+
+pub fn read_archive_manifest(archive_path: &Path) -> Result<BackupArchiveManifest, String> {
+    let mut archive = open_archive(archive_path)?;
+    let content = read_entry(&mut archive, MANIFEST_ENTRY_NAME)?;
+
+    serde_json::from_slice(&content).map_err(|e| format!("Failed to parse backup manifest: {}", e))
+}
+
+/// Read the archived files, checking each against the hash in the manifest. Nothing is
+/// returned unless every file is intact, so a damaged archive never half-restores.
+pub fn read_archived_files(archive_path: &Path) -> Result<Vec<(ArchivedFile, Vec<u8>)>, String> {
+    let manifest = read_archive_manifest(archive_path)?;
+    let mut archive = open_archive(archive_path)?;
+
+    let mut contents = Vec::new();
+    for file in manifest.files {
+        let content = read_entry(&mut archive, &format!("{}/{}", FILES_FOLDER, file.name))?;
+        if sha256_hex(&content) != file.sha256 {
+            return Err(format!(
+                "Backup archive is damaged: {} does not match its hash",
+                file.name
+            ));
+        }
+        contents.push((file, content));
+    }
+
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("GSX");
+        let archive_dir = dir.path().join("backups");
+        fs::create_dir_all(target.join("sub")).unwrap();
+        fs::write(target.join("eddm-aerosoft.ini"), "profile").unwrap();
+        fs::write(target.join("sub").join("helper.py"), "helper").unwrap();
+
+        (dir, target, archive_dir)
+    }
+
+    fn archive_count(archive_dir: &Path) -> usize {
+        fs::read_dir(archive_dir).unwrap().count()
+    }
+
+    #[test]
+    fn skips_an_archive_when_nothing_changed() {
+        let (_dir, target, archive_dir) = setup();
+        let progress = ProgressReporter::default();
+
+        let first = create_backup_archive(&target, &archive_dir, &[], &progress).unwrap();
+        let second = create_backup_archive(&target, &archive_dir, &[], &progress).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(archive_count(&archive_dir), 1);
+    }
+
+    #[test]
+    fn changed_files_need_a_new_archive() {
+        let (_dir, target, archive_dir) = setup();
+        create_backup_archive(&target, &archive_dir, &[], &ProgressReporter::default()).unwrap();
+
+        fs::write(target.join("eddm-aerosoft.ini"), "edited").unwrap();
+        assert_eq!(
+            unchanged_archive(&target, &archive_dir, &read_real_files(&target).unwrap()),
+            None
+        );
+
+        fs::write(target.join("eddm-aerosoft.ini"), "profile").unwrap();
+        fs::write(target.join("egll-justsim.ini"), "new").unwrap();
+        assert_eq!(
+            unchanged_archive(&target, &archive_dir, &read_real_files(&target).unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn passes_over_archives_of_other_folders() {
+        let (dir, target, archive_dir) = setup();
+        let other = dir.path().join("GSX 2024");
+        fs::create_dir_all(&other).unwrap();
+        fs::write(other.join("eddm-aerosoft.ini"), "profile").unwrap();
+        create_backup_archive(&other, &archive_dir, &[], &ProgressReporter::default()).unwrap();
+
+        fs::remove_dir_all(target.join("sub")).unwrap();
+        // Same files, but backed up from the other folder
+        assert_eq!(
+            unchanged_archive(&target, &archive_dir, &read_real_files(&target).unwrap()),
+            None
+        );
+    }
+}
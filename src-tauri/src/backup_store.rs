@@ -0,0 +1,230 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::create_profile_symlink::helpers;
+use crate::managed_manifest;
+
+// Backup folders only hold a manifest; the file contents live once each in the object
+// store, named by their hash, inside the manager's folder in the GSX folder
+const OBJECTS_DIR_NAME: &str = "backup-objects";
+pub const BACKUP_MANIFEST_NAME: &str = "backup.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StoredFile {
+    pub name: String,
+    pub size_bytes: u64,
+    pub sha256: String, // Lower case hex, also the object's file name
+}
+
+/// Written as backup.json into every backup folder
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct BackupManifest {
+    pub created_at: u64, // Unix seconds
+    pub files: Vec<StoredFile>,
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+pub fn objects_dir(target_dir: &Path) -> PathBuf {
+    managed_manifest::manifest_dir(target_dir).join(OBJECTS_DIR_NAME)
+}
+
+// Hashes are hex only, so an object path never leaves the store
+fn object_path(target_dir: &Path, sha256: &str) -> Result<PathBuf, String> {
+    if sha256.is_empty() || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid backup object hash: {}", sha256));
+    }
+
+    Ok(objects_dir(target_dir).join(sha256))
+}
+
+/// Add a file's content to the store unless an identical one is already there
+pub fn store_object(target_dir: &Path, content: &[u8]) -> Result<String, String> {
+    let sha256 = sha256_hex(content);
+    let path = object_path(target_dir, &sha256)?;
+
+    if path.exists() {
+        return Ok(sha256);
+    }
+
+    fs::create_dir_all(objects_dir(target_dir))
+        .map_err(|e| format!("Failed to create backup store: {}", e))?;
+
+    // Written under a temporary name so an interrupted write never leaves a bad object
+    let partial_path = path.with_extension("partial");
+    fs::write(&partial_path, content)
+        .map_err(|e| format!("Failed to write backup object: {}", e))?;
+    fs::rename(&partial_path, &path)
+        .map_err(|e| format!("Failed to write backup object: {}", e))?;
+
+    Ok(sha256)
+}
+
+fn read_object(target_dir: &Path, file: &StoredFile) -> Result<Vec<u8>, String> {
+    let content = fs::read(object_path(target_dir, &file.sha256)?)
+        .map_err(|e| format!("Failed to read backed up {}: {}", file.name, e))?;
+
+    if sha256_hex(&content) != file.sha256 {
+        return Err(format!(
+            "Backup store is damaged: {} does not match its hash",
+            file.name
+        ));
+    }
+
+    Ok(content)
+}
+
+pub fn load_backup_manifest(backup_dir: &Path) -> Result<Option<BackupManifest>, String> {
+    let path = backup_dir.join(BACKUP_MANIFEST_NAME);
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read backup manifest: {}", e))?;
+
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse backup manifest: {}", e))
+}
+
+pub fn save_backup_manifest(backup_dir: &Path, manifest: &BackupManifest) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize backup manifest: {}", e))?;
+
+    fs::write(backup_dir.join(BACKUP_MANIFEST_NAME), content)
+        .map_err(|e| format!("Failed to write backup manifest: {}", e))
+}
+
+/// Where a backed up file goes when restored. Names are relative to the GSX folder and
+/// may not leave it. Missing subfolders are created.
+pub fn restore_path(target_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(name);
+    let is_plain = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if name.is_empty() || !is_plain {
+        return Err(format!("Invalid file name in backup: {}", name));
+    }
+
+    let path = target_dir.join(relative);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create folder for {}: {}", name, e))?;
+    }
+
+    Ok(path)
+}
+
+// Backups made before the store existed hold copies of the files instead of a manifest
+fn legacy_files(backup_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries =
+        fs::read_dir(backup_dir).map_err(|e| format!("Failed to read backup directory: {}", e))?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+fn file_name(path: &Path) -> Result<String, String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| format!("Failed to get file name from {:?}", path))
+}
+
+/// The files in a backup folder with their hashes, sorted by name
+pub fn backup_contents(backup_dir: &Path) -> Result<Vec<StoredFile>, String> {
+    let mut files = match load_backup_manifest(backup_dir)? {
+        Some(manifest) => manifest.files,
+        None => legacy_files(backup_dir)?
+            .iter()
+            .map(|path| {
+                let content =
+                    fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+                Ok(StoredFile {
+                    name: file_name(path)?,
+                    size_bytes: content.len() as u64,
+                    sha256: sha256_hex(&content),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+    };
+
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+/// Read every file of a backup folder. Files from the store are checked against their
+/// hash, and nothing is returned unless all of them are intact.
+pub fn read_backup(target_dir: &Path, backup_dir: &Path) -> Result<Vec<(String, Vec<u8>)>, String> {
+    match load_backup_manifest(backup_dir)? {
+        Some(manifest) => manifest
+            .files
+            .iter()
+            .map(|file| Ok((file.name.clone(), read_object(target_dir, file)?)))
+            .collect(),
+        None => legacy_files(backup_dir)?
+            .iter()
+            .map(|path| {
+                let content =
+                    fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+                Ok((file_name(path)?, content))
+            })
+            .collect(),
+    }
+}
+
+/// Delete the objects no backup manifest refers to anymore and return how many were removed
+pub fn collect_garbage(target_dir: &Path) -> Result<usize, String> {
+    let objects_dir = objects_dir(target_dir);
+    if !objects_dir.exists() {
+        return Ok(0);
+    }
+
+    let mut referenced = HashSet::new();
+    let entries =
+        fs::read_dir(target_dir).map_err(|e| format!("Failed to read target directory: {}", e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+
+        if helpers::is_backup_dir(&path) {
+            if let Some(manifest) = load_backup_manifest(&path)? {
+                referenced.extend(manifest.files.into_iter().map(|file| file.sha256));
+            }
+        }
+    }
+
+    let mut removed = 0;
+    let objects =
+        fs::read_dir(&objects_dir).map_err(|e| format!("Failed to read backup store: {}", e))?;
+
+    for object in objects {
+        let object = object.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+
+        if !referenced.contains(&*object.file_name().to_string_lossy()) {
+            fs::remove_file(object.path())
+                .map_err(|e| format!("Failed to remove backup object: {}", e))?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
@@ -0,0 +1,349 @@
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Component, Path};
+
+// Layout of the compiled scenery files, as documented for FSX and still used by MSFS:
+// a file header, a table of section headers, and per section a table of subsection headers
+// that point at the records
+const BGL_MAGIC: u32 = 0x19920201;
+const FILE_HEADER_SIZE: usize = 0x38;
+const SECTION_HEADER_SIZE: usize = 20;
+const AIRPORT_SECTION_TYPE: u32 = 0x0003;
+
+// Offset of the packed ICAO ident inside an airport record
+const AIRPORT_IDENT_OFFSET: usize = 0x28;
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct PackageLayout {
+    content: Vec<LayoutEntry>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct LayoutEntry {
+    path: String, // Relative to the package folder, with "/" separators
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let slice = bytes.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes([slice[0], slice[1], slice[2], slice[3]]))
+}
+
+fn truncated() -> String {
+    "BGL file is truncated".to_string()
+}
+
+// Read `size` bytes at `offset`, checked against the file length first so a damaged size
+// never allocates more than the file holds
+fn read_at<R: Read + Seek>(
+    reader: &mut R,
+    file_len: u64,
+    offset: usize,
+    size: usize,
+) -> Result<Vec<u8>, String> {
+    let end = (offset as u64)
+        .checked_add(size as u64)
+        .ok_or_else(truncated)?;
+    if end > file_len {
+        return Err(truncated());
+    }
+
+    let mut buffer = vec![0; size];
+    reader
+        .seek(SeekFrom::Start(offset as u64))
+        .and_then(|_| reader.read_exact(&mut buffer))
+        .map_err(|e| format!("Failed to read BGL file: {}", e))?;
+
+    Ok(buffer)
+}
+
+/// Decode an ident packed as base 38 digits: 0 is a space, 2-11 the digits and 12-37 the
+/// letters. ICAO idents are additionally shifted left by 5 bits.
+pub fn decode_ident(packed: u32) -> String {
+    let mut value = packed >> 5;
+    let mut chars = Vec::new();
+
+    while value > 0 {
+        let code = value % 38;
+        value /= 38;
+
+        chars.push(match code {
+            2..=11 => (b'0' + (code - 2) as u8) as char,
+            12..=37 => (b'A' + (code - 12) as u8) as char,
+            _ => ' ',
+        });
+    }
+
+    chars.iter().rev().collect::<String>().trim().to_string()
+}
+
+// The idents of the airport records in one subsection's data
+fn read_records(data: &[u8], record_count: usize, idents: &mut Vec<String>) -> Result<(), String> {
+    let mut record = 0;
+
+    for _ in 0..record_count {
+        // Every record starts with a 2 byte id and its size including this header
+        let record_size = read_u32(data, record + 2).ok_or_else(truncated)? as usize;
+        if record_size < AIRPORT_IDENT_OFFSET + 4 || record + record_size > data.len() {
+            return Err("BGL airport record is damaged".to_string());
+        }
+
+        let ident =
+            decode_ident(read_u32(data, record + AIRPORT_IDENT_OFFSET).ok_or_else(truncated)?);
+        if !ident.is_empty() && !idents.contains(&ident) {
+            idents.push(ident);
+        }
+
+        record += record_size;
+    }
+
+    Ok(())
+}
+
+/// The ICAO idents of the airport records in a BGL file, in file order. Only the headers
+/// and the airport subsections are read, not the rest of the file.
+pub fn read_airport_idents<R: Read + Seek>(reader: &mut R) -> Result<Vec<String>, String> {
+    let file_len = reader
+        .seek(SeekFrom::End(0))
+        .map_err(|e| format!("Failed to read BGL file: {}", e))?;
+
+    let header =
+        read_at(reader, file_len, 0, FILE_HEADER_SIZE).map_err(|_| "Not a BGL file".to_string())?;
+    if read_u32(&header, 0) != Some(BGL_MAGIC) {
+        return Err("Not a BGL file".to_string());
+    }
+
+    let section_count = read_u32(&header, 0x14).ok_or_else(truncated)? as usize;
+    let sections = read_at(
+        reader,
+        file_len,
+        FILE_HEADER_SIZE,
+        section_count
+            .checked_mul(SECTION_HEADER_SIZE)
+            .ok_or_else(truncated)?,
+    )?;
+    let mut idents = Vec::new();
+
+    for section in sections.chunks_exact(SECTION_HEADER_SIZE) {
+        if read_u32(section, 0) != Some(AIRPORT_SECTION_TYPE) {
+            continue;
+        }
+
+        // Subsection headers are 16 bytes, or 20 when this flag bit is set
+        let size_flag = read_u32(section, 4).ok_or_else(truncated)?;
+        let subsection_header_size = (((size_flag & 0x10000) | 0x40000) >> 0x0E) as usize;
+        let subsection_count = read_u32(section, 8).ok_or_else(truncated)? as usize;
+        let first_subsection = read_u32(section, 12).ok_or_else(truncated)? as usize;
+
+        let subsections = read_at(
+            reader,
+            file_len,
+            first_subsection,
+            subsection_count
+                .checked_mul(subsection_header_size)
+                .ok_or_else(truncated)?,
+        )?;
+
+        for subsection in subsections.chunks_exact(subsection_header_size) {
+            // The record count, data offset and data size are the last three fields
+            let fields = subsection_header_size - 12;
+            let record_count = read_u32(subsection, fields).ok_or_else(truncated)? as usize;
+            let data_offset = read_u32(subsection, fields + 4).ok_or_else(truncated)? as usize;
+            let data_size = read_u32(subsection, fields + 8).ok_or_else(truncated)? as usize;
+
+            let data = read_at(reader, file_len, data_offset, data_size)?;
+            read_records(&data, record_count, &mut idents)?;
+        }
+    }
+
+    Ok(idents)
+}
+
+pub fn read_bgl_file(path: &Path) -> Result<Vec<String>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+
+    read_airport_idents(&mut BufReader::new(file)).map_err(|e| format!("{}: {:?}", e, path))
+}
+
+/// The scenery BGL files a package lists in its layout.json
+pub fn scenery_bgl_files(package_dir: &Path) -> Result<Vec<String>, String> {
+    let layout_path = package_dir.join("layout.json");
+    if !layout_path.is_file() {
+        return Ok(Vec::new());
+    }
+
+    let content =
+        fs::read(&layout_path).map_err(|e| format!("Failed to read {:?}: {}", layout_path, e))?;
+    let content = String::from_utf8_lossy(&content);
+    let layout: PackageLayout = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("Failed to parse {:?}: {}", layout_path, e))?;
+
+    Ok(layout
+        .content
+        .into_iter()
+        .map(|entry| entry.path)
+        .filter(|path| {
+            let lower = path.to_lowercase();
+            lower.starts_with("scenery/") && lower.ends_with(".bgl")
+        })
+        .collect())
+}
+
+/// The ICAO idents of every airport in a package's scenery. Files that cannot be read, such
+/// as encrypted Marketplace scenery, are skipped. None when no scenery file could be read,
+/// so the package's airports are unknown rather than none.
+pub fn package_airport_idents(package_dir: &Path) -> Result<Option<Vec<String>>, String> {
+    let mut idents = Vec::new();
+    let mut read_any = false;
+
+    for relative in scenery_bgl_files(package_dir)? {
+        // Layout paths come from the package, so never follow one out of it
+        let relative_path = Path::new(&relative);
+        if !relative_path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            continue;
+        }
+        let path = package_dir.join(relative_path);
+
+        match read_bgl_file(&path) {
+            Ok(found) => {
+                read_any = true;
+                for ident in found {
+                    if !idents.contains(&ident) {
+                        idents.push(ident);
+                    }
+                }
+            }
+            Err(e) => println!("Skipping scenery file: {}", e),
+        }
+    }
+
+    Ok(read_any.then_some(idents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/bgl")
+            .join(name);
+        fs::read(&path).unwrap_or_else(|e| panic!("missing fixture {:?}: {}", path, e))
+    }
+
+    fn idents(name: &str) -> Result<Vec<String>, String> {
+        read_airport_idents(&mut Cursor::new(fixture(name)))
+    }
+
+    fn encode_ident(ident: &str) -> u32 {
+        let value = ident.chars().fold(0, |value, c| {
+            let code = match c {
+                '0'..='9' => c as u32 - '0' as u32 + 2,
+                'A'..='Z' => c as u32 - 'A' as u32 + 12,
+                _ => 0,
+            };
+            value * 38 + code
+        });
+        value << 5
+    }
+
+    #[test]
+    fn decodes_base38_idents() {
+        assert_eq!(decode_ident(encode_ident("LEPA")), "LEPA");
+        assert_eq!(decode_ident(encode_ident("K1S5")), "K1S5");
+        assert_eq!(decode_ident(encode_ident("EGC")), "EGC");
+        // The low 5 bits are not part of the ident
+        assert_eq!(decode_ident(encode_ident("EDDM") | 0x1f), "EDDM");
+        assert_eq!(decode_ident(0), "");
+    }
+
+    #[test]
+    fn reads_airport_sections_and_skips_others() {
+        assert_eq!(idents("airports.bgl").unwrap(), vec!["LEPA", "LESJ"]);
+        assert!(idents("no-airports.bgl").unwrap().is_empty());
+    }
+
+    #[test]
+    fn reads_wide_subsection_headers_and_dedupes() {
+        assert_eq!(
+            idents("airports-wide.bgl").unwrap(),
+            vec!["LEMH", "K1S5", "LEPA"]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_and_damaged_files() {
+        assert_eq!(
+            idents("truncated.bgl").unwrap_err(),
+            "BGL file is truncated"
+        );
+        assert_eq!(
+            idents("corrupt-record.bgl").unwrap_err(),
+            "BGL airport record is damaged"
+        );
+        assert!(read_airport_idents(&mut Cursor::new(b"encrypted".to_vec())).is_err());
+
+        // A section count far beyond the file must fail without allocating for it
+        let mut huge = fixture("airports.bgl");
+        huge[0x14..0x18].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read_airport_idents(&mut Cursor::new(huge)).is_err());
+    }
+
+    #[test]
+    fn reads_the_scenery_listed_in_layout_json() {
+        let package = tempfile::tempdir().unwrap();
+        let scenery = package.path().join("scenery/global/scenery");
+        fs::create_dir_all(&scenery).unwrap();
+        fs::write(scenery.join("a.bgl"), fixture("airports.bgl")).unwrap();
+        fs::write(scenery.join("b.BGL"), fixture("airports-wide.bgl")).unwrap();
+        fs::write(scenery.join("c.bgl"), fixture("corrupt-record.bgl")).unwrap();
+        fs::write(package.path().join("unlisted.bgl"), fixture("airports.bgl")).unwrap();
+        fs::write(
+            package.path().join("layout.json"),
+            r#"{"content": [
+                {"path": "scenery/global/scenery/a.bgl", "size": 208},
+                {"path": "scenery/global/scenery/b.BGL"},
+                {"path": "scenery/global/scenery/c.bgl"},
+                {"path": "scenery/../unlisted.bgl"},
+                {"path": "texture/t.bgl"}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            package_airport_idents(package.path()).unwrap(),
+            Some(vec![
+                "LEPA".to_string(),
+                "LESJ".to_string(),
+                "LEMH".to_string(),
+                "K1S5".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn tells_unreadable_scenery_from_scenery_without_airports() {
+        let package = tempfile::tempdir().unwrap();
+        let scenery = package.path().join("scenery");
+        fs::create_dir_all(&scenery).unwrap();
+        fs::write(scenery.join("a.bgl"), fixture("corrupt-record.bgl")).unwrap();
+        fs::write(
+            package.path().join("layout.json"),
+            r#"{"content": [{"path": "scenery/a.bgl"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(package_airport_idents(package.path()).unwrap(), None);
+
+        fs::write(scenery.join("a.bgl"), fixture("no-airports.bgl")).unwrap();
+        assert_eq!(
+            package_airport_idents(package.path()).unwrap(),
+            Some(Vec::new())
+        );
+    }
+}
@@ -1,24 +1,94 @@
-use std::env;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use tauri::AppHandle;
 use tauri_plugin_dialog::DialogExt;
 
-// Helper functions module
+use crate::activation_diff;
+use crate::activation_history;
+use crate::activation_progress::{ActivationProgress, ProgressReporter};
+use crate::activation_report::{ActivationError, ActivationReport, SkippedSource};
+use crate::activation_transaction;
+use crate::backup_archive;
+use crate::gsx_target::get_gsx_target_dir;
+use crate::managed_manifest::{Ownership, UnmanagedPolicy};
+use crate::profile_backups::{self, BackupFormat};
+use crate::profile_conflicts::{self, ConflictPolicy};
+use crate::settings::{self, BackendSettings};
+use crate::sim_guard::{SimGuard, SimRunningPolicy};
+
+// Helper functions module.
+//
+// How the GSX folder is treated below the top level:
+// - Managed entries are always top-level: files, file links, and for selected folders (such
+//   as a Python helper package next to a profile) a directory link or a copied folder. They
+//   are removed as a whole and links are never followed.
+// - Any other real subfolder belongs to the user. It is never removed or linked over, but
+//   its files are included in backups, with their path relative to the GSX folder.
+// - backup-* folders and the manager's own .gsx-* folders are skipped everywhere.
 pub mod helpers {
     use std::fs;
-    use std::os::windows::fs as win_fs;
     use std::path::{Path, PathBuf};
     use std::time::{SystemTime, UNIX_EPOCH};
 
-    // Creates a backup of existing profiles
-    pub fn create_profile_backup(target_dir: &Path) -> Result<(), String> {
+    use crate::activation_progress::{ActivationProgress, ProgressReporter};
+    use crate::activation_report::{LinkOutcome, LinkedFile};
+    use crate::backup_store::{self, BackupManifest, StoredFile};
+    use crate::link_strategy::{self, LinkStrategy};
+    use crate::managed_manifest::{Ownership, UnmanagedPolicy};
+    use crate::profile_backups;
+
+    // Creates a backup of existing profiles and returns the backup folder. The folder only
+    // holds a manifest of hashes, the contents go to the backup store once per distinct file.
+    // If nothing changed since the newest backup, that one is returned instead.
+    pub fn create_profile_backup(
+        target_dir: &Path,
+        progress: &ProgressReporter,
+    ) -> Result<PathBuf, String> {
         // Get current timestamp using std library
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map_err(|e| format!("Failed to get system time: {}", e))?
             .as_secs();
 
+        // Hash all files from the target directory, including those in subfolders
+        let paths = collect_real_files(target_dir)?;
+
+        let mut files = Vec::new();
+        for path in &paths {
+            let content =
+                fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+
+            files.push((
+                StoredFile {
+                    name: relative_name(target_dir, path),
+                    size_bytes: content.len() as u64,
+                    sha256: backup_store::sha256_hex(&content),
+                },
+                content,
+            ));
+        }
+
+        // Same order as backup_contents, so the previous backup can be compared directly
+        files.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+
+        // A backup identical to the previous one would only add clutter
+        if let Some(latest) = profile_backups::list_backups(target_dir, None)?.first() {
+            let latest_dir = PathBuf::from(&latest.path);
+            let unchanged = backup_store::backup_contents(&latest_dir)?
+                .iter()
+                .eq(files.iter().map(|(file, _)| file));
+
+            if unchanged {
+                println!(
+                    "Skipping backup, nothing changed since {}",
+                    latest_dir.to_string_lossy()
+                );
+                return Ok(latest_dir);
+            }
+        }
+
         // Create backup directory in the same folder as target_dir
         let backup_dir = target_dir.join(format!("backup-{}", timestamp));
 
@@ -29,41 +99,43 @@ pub mod helpers {
         fs::create_dir_all(&backup_dir)
             .map_err(|e| format!("Failed to create backup directory: {}", e))?;
 
-        // Copy all files from target directory to backup directory
-        let entries = fs::read_dir(target_dir)
-            .map_err(|e| format!("Failed to read target directory: {}", e))?;
-
-        for entry in entries {
-            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-            let path = entry.path();
-
-            // Skip backup directories
-            if path.is_dir()
-                && path
-                    .file_name()
-                    .map_or(false, |name| name.to_string_lossy().starts_with("backup-"))
-            {
-                continue;
+        for (index, (file, content)) in files.iter().enumerate() {
+            // A half-written backup is useless, so drop it when cancelled
+            if let Err(e) = progress.check_cancelled() {
+                let _ = fs::remove_dir_all(&backup_dir);
+                let _ = backup_store::collect_garbage(target_dir);
+                return Err(e);
             }
 
-            if path.is_file() && !path.is_symlink() {
-                let file_name = path
-                    .file_name()
-                    .ok_or_else(|| format!("Failed to get file name from {:?}", path))?;
-                let dest_path = backup_dir.join(file_name);
+            println!("Backing up file: {} ({})", file.name, file.sha256);
 
-                println!("Backing up file: {:?} to {:?}", path, dest_path);
+            backup_store::store_object(target_dir, content)?;
 
-                fs::copy(&path, &dest_path)
-                    .map_err(|e| format!("Failed to copy file to backup: {}", e))?;
-            }
+            progress.report(ActivationProgress::BackupCopied {
+                current: index + 1,
+                total: files.len(),
+                file: target_dir.join(&file.name).to_string_lossy().to_string(),
+            });
         }
 
-        Ok(())
+        // Written last, so a backup folder with a manifest is always complete
+        backup_store::save_backup_manifest(
+            &backup_dir,
+            &BackupManifest {
+                created_at: timestamp,
+                files: files.into_iter().map(|(file, _)| file).collect(),
+            },
+        )?;
+
+        Ok(backup_dir)
     }
 
-    // Remove existing symlinks in the target directory
-    pub fn remove_existing_symlinks(target_dir: &Path) -> Result<(), String> {
+    // The entries the manager created in the target directory. Files the user put there by
+    // hand are left out.
+    pub fn managed_entries(target_dir: &Path) -> Result<Vec<PathBuf>, String> {
+        let ownership = Ownership::load(target_dir, UnmanagedPolicy::Preserve)?;
+        let mut managed = Vec::new();
+
         let entries = fs::read_dir(target_dir)
             .map_err(|e| format!("Failed to read target directory: {}", e))?;
 
@@ -71,38 +143,45 @@ pub mod helpers {
             let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
             let path = entry.path();
 
-            // Skip backup directories
-            if path.is_dir()
-                && path
-                    .file_name()
-                    .map_or(false, |name| name.to_string_lossy().starts_with("backup-"))
-            {
+            // Skip backup directories and the manager's own folders
+            if is_backup_dir(&path) || is_manager_dir(&path) {
                 continue;
             }
 
-            if path.is_file() || path.is_symlink() {
-                println!("Removing file or symlink: {:?}", path);
-                fs::remove_file(&path)
-                    .map_err(|e| format!("Failed to remove file or symlink {:?}: {}", path, e))?;
+            // Also removes directory links and copied folders, but never user folders
+            let owned = if path.is_dir() && !path.is_symlink() {
+                ownership.is_managed_folder(&path)
+            } else {
+                ownership.owns(&path)
+            };
+
+            if owned {
+                managed.push(path);
             }
         }
 
-        Ok(())
+        Ok(managed)
     }
 
-    // Create symlinks for selected profile files
+    // Create symlinks (or hardlinks/copies, depending on the strategy) for selected profile files
     pub fn create_profile_symlinks(
         file_paths: Vec<String>,
         target_dir: &Path,
-    ) -> Result<usize, String> {
-        let mut created_count = 0;
+        strategy: LinkStrategy,
+        progress: &ProgressReporter,
+    ) -> Result<LinkOutcome, String> {
+        let mut outcome = LinkOutcome::default();
+        let total = file_paths.len();
+
+        for (index, file_path) in file_paths.into_iter().enumerate() {
+            progress.check_cancelled()?;
 
-        for file_path in file_paths {
             let source_path = PathBuf::from(&file_path);
 
-            // Skip if source doesn't exist
+            // Skip if source doesn't exist, but report it
             if !source_path.exists() {
                 println!("Warning: Source file does not exist: {}", file_path);
+                outcome.missing_sources.push(file_path);
                 continue;
             }
 
@@ -119,32 +198,70 @@ pub mod helpers {
             // Remove existing file at target path if it exists
             if target_path.exists() || target_path.is_symlink() {
                 println!("Removing existing file at target path: {:?}", target_path);
-                if target_path.is_dir() {
-                    fs::remove_dir_all(&target_path)
-                        .map_err(|e| format!("Failed to remove existing directory: {}", e))?;
-                } else {
-                    fs::remove_file(&target_path)
-                        .map_err(|e| format!("Failed to remove existing file: {}", e))?;
-                }
+                remove_entry(&target_path)?;
             }
 
             // Create symlink
-            if source_path.is_dir() {
-                win_fs::symlink_dir(&source_path, &target_path)
-                    .map_err(|e| format!("Failed to create directory symlink: {}", e))?;
-            } else {
-                win_fs::symlink_file(&source_path, &target_path)
-                    .map_err(|e| format!("Failed to create file symlink: {}", e))?;
+            let used_strategy = link_strategy::create_link(&source_path, &target_path, strategy)?;
+            if used_strategy != strategy {
+                println!("Linked {:?} using {:?}", target_path, used_strategy);
             }
 
-            created_count += 1;
+            progress.report(ActivationProgress::Linked {
+                current: index + 1,
+                total,
+                source: file_path.clone(),
+            });
+
+            outcome.linked.push(LinkedFile {
+                source: file_path,
+                target: target_path.to_string_lossy().to_string(),
+                strategy: used_strategy,
+            });
         }
 
-        Ok(created_count)
+        Ok(outcome)
     }
 
-    // Check if directory has actual files (not just directories or backup folders)
-    pub fn has_actual_files(dir_path: &Path) -> Result<bool, String> {
+    // Check if a path is one of the backup-<timestamp> folders created by create_profile_backup
+    pub fn is_backup_dir(path: &Path) -> bool {
+        path.is_dir()
+            && path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("backup-"))
+    }
+
+    // Check if a path is one of the manager's own folders, e.g. .gsx-profile-manager
+    pub fn is_manager_dir(path: &Path) -> bool {
+        path.is_dir()
+            && path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(".gsx-"))
+    }
+
+    // Remove a file, symlink or directory without following symlinks
+    pub fn remove_entry(path: &Path) -> Result<(), String> {
+        let is_real_dir = path.is_dir() && !path.is_symlink();
+        let result = if is_real_dir {
+            fs::remove_dir_all(path)
+        } else {
+            // Directory links need remove_dir on Windows
+            fs::remove_file(path).or_else(|_| fs::remove_dir(path))
+        };
+
+        result.map_err(|e| format!("Failed to remove {:?}: {}", path, e))
+    }
+
+    // Collect the real files in a directory and its subfolders, sorted. Symlinks are not
+    // followed, and backup and manager folders are skipped.
+    pub fn collect_real_files(dir_path: &Path) -> Result<Vec<PathBuf>, String> {
+        let mut files = Vec::new();
+        collect_real_files_into(dir_path, &mut files)?;
+        files.sort();
+        Ok(files)
+    }
+
+    fn collect_real_files_into(dir_path: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
         let entries =
             fs::read_dir(dir_path).map_err(|e| format!("Failed to read directory: {}", e))?;
 
@@ -152,112 +269,624 @@ pub mod helpers {
             let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
             let path = entry.path();
 
-            // Skip backup directories
-            if path.is_dir()
-                && path
-                    .file_name()
-                    .map_or(false, |name| name.to_string_lossy().starts_with("backup-"))
-            {
+            if path.is_symlink() || is_backup_dir(&path) || is_manager_dir(&path) {
                 continue;
             }
 
-            // If it's a file or symlink, return true
-            if path.is_file() && !path.is_symlink() {
-                return Ok(true);
+            if path.is_dir() {
+                collect_real_files_into(&path, files)?;
+            } else if path.is_file() {
+                files.push(path);
             }
         }
 
-        // No files found
-        Ok(false)
+        Ok(())
     }
+
+    // Path of a file relative to the GSX folder with "/" separators, e.g. "scripts/eddm.py"
+    pub fn relative_name(target_dir: &Path, path: &Path) -> String {
+        let relative = path.strip_prefix(target_dir).unwrap_or(path);
+        relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    // Check if directory has actual files (not just links or backup folders), in any subfolder
+    pub fn has_actual_files(dir_path: &Path) -> Result<bool, String> {
+        Ok(!collect_real_files(dir_path)?.is_empty())
+    }
+}
+
+/// Whether files the manager does not own may be replaced or removed
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    // Ask the user, as the activate_profiles command does with dialogs
+    #[default]
+    Ask,
+    Always,
+    // Fail instead of touching them
+    Never,
+}
+
+/// Options for an activation. Anything left out falls back to the backend settings.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ActivationOptions {
+    pub conflict_policy: Option<ConflictPolicy>,
+    pub unmanaged_policy: Option<UnmanagedPolicy>,
+    pub overwrite: OverwritePolicy,
+    // Back up the GSX folder before overwriting. With Ask, the user is asked instead.
+    pub backup: bool,
+    // Profiles applied in the frontend, recorded in zip backups
+    pub profile_ids: Vec<String>,
+    pub sim_running_policy: Option<SimRunningPolicy>,
+}
+
+/// What the user is asked before unmanaged files are overwritten
+pub struct OverwritePrompt<'a> {
+    pub displaced: &'a [PathBuf], // Unmanaged files that would be replaced or removed
+    pub can_back_up: bool,        // Whether there are real files a backup would keep
+}
+
+pub struct OverwriteAnswer {
+    pub proceed: bool,
+    pub backup: bool,
+}
+
+/// Activate profiles into the target folder without any UI. Prompts only happen through
+/// `prompt`, and only when `options.overwrite` is Ask. Progress goes to `progress`, which
+/// can also cancel the activation until the new links are moved in. `guard` decides what
+/// happens while the simulator is running, before anything else is done.
+pub fn run_activation(
+    target_dir: &Path,
+    selected_files: Vec<String>,
+    settings: &BackendSettings,
+    options: &ActivationOptions,
+    progress: &ProgressReporter,
+    guard: &SimGuard,
+    prompt: &mut dyn FnMut(&OverwritePrompt) -> Result<OverwriteAnswer, ActivationError>,
+) -> Result<ActivationReport, ActivationError> {
+    progress.report(ActivationProgress::Started {
+        total: selected_files.len(),
+    });
+
+    let result = guard
+        .check(options.sim_running_policy, progress)
+        .and_then(|sim_running| {
+            let mut report = activate_selection(
+                target_dir,
+                selected_files,
+                settings,
+                options,
+                progress,
+                prompt,
+            )?;
+            report.sim_running = sim_running;
+            Ok(report)
+        });
+
+    progress.report(match &result {
+        Ok(report) => ActivationProgress::Finished {
+            duration_ms: report.duration_ms,
+        },
+        Err(ActivationError::Cancelled) => ActivationProgress::Cancelled,
+        Err(e) => ActivationProgress::Failed {
+            message: e.to_string(),
+        },
+    });
+
+    result
+}
+
+fn activate_selection(
+    target_dir: &Path,
+    selected_files: Vec<String>,
+    settings: &BackendSettings,
+    options: &ActivationOptions,
+    progress: &ProgressReporter,
+    prompt: &mut dyn FnMut(&OverwritePrompt) -> Result<OverwriteAnswer, ActivationError>,
+) -> Result<ActivationReport, ActivationError> {
+    let started_at = Instant::now();
+
+    // A cancelled step reports a plain error, turn it into the cancellation it is
+    let cancelled_or = |error: fn(String) -> ActivationError| {
+        move |message: String| {
+            if progress.is_cancelled() {
+                ActivationError::Cancelled
+            } else {
+                error(message)
+            }
+        }
+    };
+
+    // Create target directory if it doesn't exist
+    fs::create_dir_all(target_dir).map_err(|e| {
+        ActivationError::target_dir(format!("Failed to create target directory: {}", e))
+    })?;
+
+    // Settle profiles that would overwrite each other before anything is touched
+    let conflict_policy = options
+        .conflict_policy
+        .clone()
+        .unwrap_or_else(|| settings.conflict_policy.clone());
+    let resolution = profile_conflicts::resolve_conflicts(&selected_files, &conflict_policy)
+        .map_err(|message| ActivationError::Conflict {
+            message,
+            conflicts: profile_conflicts::detect_conflicts(&selected_files),
+        })?;
+    for conflict in &resolution.conflicts {
+        println!(
+            "Resolved {:?} conflict for {}: keeping {:?}",
+            conflict.kind, conflict.key, conflict.kept
+        );
+    }
+
+    let mut report = ActivationReport {
+        target_dir: target_dir.to_string_lossy().to_string(),
+        conflicts: resolution.conflicts,
+        skipped: resolution
+            .dropped_files
+            .into_iter()
+            .map(|source| SkippedSource {
+                source,
+                reason: "Dropped by the conflict policy".to_string(),
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let selected_files = resolution.kept_files;
+
+    // Files the manager did not create are only touched when the caller asks for it
+    let mut unmanaged_policy = options.unmanaged_policy.unwrap_or_default();
+    let ownership =
+        Ownership::load(target_dir, unmanaged_policy).map_err(ActivationError::target_dir)?;
+    let mut diff = activation_diff::diff_link_set(&selected_files, target_dir, &ownership)
+        .map_err(ActivationError::target_dir)?;
+
+    let can_back_up = helpers::has_actual_files(target_dir).map_err(ActivationError::target_dir)?;
+    let mut confirmed_backup = None;
+
+    if !diff.blocked.is_empty() {
+        // Only an explicit Preserve from the caller keeps same-named files without asking
+        if options.unmanaged_policy.is_some() {
+            let files = to_strings(&diff.blocked);
+            return Err(ActivationError::UnmanagedFiles {
+                message: format!(
+                    "Selected profiles would replace files the manager does not own: {}",
+                    files.join(", ")
+                ),
+                files,
+            });
+        }
+
+        confirmed_backup = Some(confirm_overwrite(
+            &diff.blocked,
+            options,
+            can_back_up,
+            prompt,
+        )?);
+
+        // Confirmed, so replace exactly the files that were in the way
+        unmanaged_policy = UnmanagedPolicy::Replace;
+        let ownership =
+            Ownership::load(target_dir, unmanaged_policy).map_err(ActivationError::target_dir)?;
+        diff = activation_diff::diff_link_set(&selected_files, target_dir, &ownership)
+            .map_err(ActivationError::target_dir)?;
+    }
+
+    // Unmanaged files would be replaced or removed, so check that this is wanted
+    if !diff.unmanaged_displaced.is_empty() {
+        let backup = match confirmed_backup {
+            Some(backup) => backup,
+            None => confirm_overwrite(&diff.unmanaged_displaced, options, can_back_up, prompt)?,
+        };
+
+        if backup && can_back_up {
+            let backup_path =
+                back_up_target_dir(target_dir, settings, &options.profile_ids, progress)
+                    .map_err(cancelled_or(ActivationError::backup))?;
+            report.backup = Some(backup_path.to_string_lossy().to_string());
+        }
+    }
+
+    // Now swap the new symlinks in, restoring the previous profiles if anything fails
+    let outcome = activation_transaction::activate_transactionally(
+        selected_files,
+        target_dir,
+        settings.link_strategy,
+        unmanaged_policy,
+        progress,
+    )
+    .map_err(cancelled_or(ActivationError::link))?;
+
+    report.linked = outcome.linked;
+    report.missing_sources = outcome.missing_sources;
+    report.removed = outcome.removed;
+    report.delta = outcome.delta;
+    report.duration_ms = started_at.elapsed().as_millis() as u64;
+
+    println!(
+        "Activated profiles: {} added, {} retargeted, {} removed, {} unchanged ({} missing, {} skipped) in {} ms",
+        report.delta.added.len(),
+        report.delta.retargeted.len(),
+        report.delta.removed.len(),
+        report.delta.unchanged.len(),
+        report.missing_sources.len(),
+        report.skipped.len(),
+        report.duration_ms
+    );
+
+    Ok(report)
+}
+
+/// Back up the GSX folder in the configured format and location, then apply the retention
+/// policy so backups don't pile up. `profile_ids` are recorded in zip backups.
+pub fn back_up_target_dir(
+    target_dir: &Path,
+    settings: &BackendSettings,
+    profile_ids: &[String],
+    progress: &ProgressReporter,
+) -> Result<PathBuf, String> {
+    let archive_dir = settings.backup_location.as_deref().map(Path::new);
+    let backup_path = match (settings.backup_format, archive_dir) {
+        (BackupFormat::Folder, _) => helpers::create_profile_backup(target_dir, progress),
+        (BackupFormat::Zip, Some(archive_dir)) => {
+            backup_archive::create_backup_archive(target_dir, archive_dir, profile_ids, progress)
+        }
+        (BackupFormat::Zip, None) => Err("No backup location configured".to_string()),
+    }?;
+
+    let pruned =
+        profile_backups::prune_backups(target_dir, archive_dir, &settings.backup_retention)?;
+    if !pruned.is_empty() {
+        println!("Pruned {} old backups: {:?}", pruned.len(), pruned);
+    }
+
+    Ok(backup_path)
+}
+
+fn to_strings(paths: &[PathBuf]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect()
+}
+
+// Whether unmanaged files may be replaced or removed, as the overwrite policy says or the
+// user answers. Returns whether to back up first.
+fn confirm_overwrite(
+    displaced: &[PathBuf],
+    options: &ActivationOptions,
+    can_back_up: bool,
+    prompt: &mut dyn FnMut(&OverwritePrompt) -> Result<OverwriteAnswer, ActivationError>,
+) -> Result<bool, ActivationError> {
+    match options.overwrite {
+        OverwritePolicy::Always => Ok(options.backup),
+        OverwritePolicy::Never => {
+            let files = to_strings(displaced);
+            Err(ActivationError::UnmanagedFiles {
+                message: format!(
+                    "Activation would overwrite files the manager does not own: {}",
+                    files.join(", ")
+                ),
+                files,
+            })
+        }
+        OverwritePolicy::Ask => {
+            let answer = prompt(&OverwritePrompt {
+                displaced,
+                can_back_up,
+            })?;
+            if !answer.proceed {
+                return Err(ActivationError::Cancelled);
+            }
+            Ok(answer.backup)
+        }
+    }
+}
+
+// Show a dialog and wait for the answer - true for OK/Yes, false for Cancel/No
+fn ask_user(
+    app: &AppHandle,
+    title: &str,
+    message: String,
+    kind: tauri_plugin_dialog::MessageDialogKind,
+    buttons: tauri_plugin_dialog::MessageDialogButtons,
+) -> Result<bool, ActivationError> {
+    // Create channel to receive dialog result
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    app.dialog()
+        .message(message)
+        .title(title)
+        .kind(kind)
+        .buttons(buttons)
+        .show(move |response| {
+            let _ = tx.send(response);
+        });
+
+    rx.recv().map_err(|_| ActivationError::Dialog {
+        message: "Dialog interaction failed".to_string(),
+    })
+}
+
+// Asks for confirmation and a backup with dialogs, for OverwritePolicy::Ask
+fn ask_overwrite(
+    app: &AppHandle,
+    prompt: &OverwritePrompt,
+) -> Result<OverwriteAnswer, ActivationError> {
+    let proceed = ask_user(
+        app,
+        "Warning",
+        format!(
+            "This will replace {} existing GSX profiles that were not activated by this app. Would you like to proceed?",
+            prompt.displaced.len()
+        ),
+        tauri_plugin_dialog::MessageDialogKind::Warning,
+        tauri_plugin_dialog::MessageDialogButtons::OkCancel,
+    )?;
+
+    // Only ask for backup if there are actual files to back up
+    let backup = proceed
+        && prompt.can_back_up
+        && ask_user(
+            app,
+            "Backup",
+            "Would you like to create a backup of your current profiles?".to_string(),
+            tauri_plugin_dialog::MessageDialogKind::Info,
+            tauri_plugin_dialog::MessageDialogButtons::YesNo,
+        )?;
+
+    Ok(OverwriteAnswer { proceed, backup })
 }
 
 #[tauri::command]
 pub async fn activate_profiles(
     app: AppHandle,
     selected_files: Vec<String>,
-) -> Result<String, String> {
-    // Get the AppData\Roaming folder directly using environment variables
-    let roaming_dir = env::var("APPDATA")
-        .map_err(|e| format!("Failed to get APPDATA environment variable: {}", e))?;
+    options: Option<ActivationOptions>,
+) -> Result<ActivationReport, ActivationError> {
+    let target_dir = get_gsx_target_dir(&app).map_err(ActivationError::target_dir)?;
+    let mut settings = settings::load_settings(&app).map_err(ActivationError::settings)?;
+    let options = options.unwrap_or_default();
+
+    // run_activation has no app to find the default backup location with
+    let archive_dir =
+        profile_backups::get_archive_dir(&app, &settings).map_err(ActivationError::settings)?;
+    settings.backup_location = Some(archive_dir.to_string_lossy().to_string());
+    let progress = ProgressReporter::for_app(&app);
+    let guard = SimGuard::system(settings.sim_guard.clone());
+
+    // Dialogs wait for the user, so keep them off the async worker threads
+    tauri::async_runtime::spawn_blocking(move || {
+        // What was active before, so the history can undo this activation
+        let previous_files = activation_history::active_files(&target_dir).unwrap_or_default();
+
+        let report = run_activation(
+            &target_dir,
+            selected_files,
+            &settings,
+            &options,
+            &progress,
+            &guard,
+            &mut |prompt| ask_overwrite(&app, prompt),
+        );
+        progress.finish();
+        let report = report?;
+
+        activation_history::record_activation(&app, previous_files, &report);
+        Ok(report)
+    })
+    .await
+    .map_err(|e| ActivationError::Dialog {
+        message: format!("Activation task failed: {}", e),
+    })?
+}
 
-    // Create path to C:\Users\[user]\AppData\Roaming\Virtuali\GSX\MSFS
-    let target_dir = PathBuf::from(roaming_dir)
-        .join("Virtuali")
-        .join("GSX")
-        .join("MSFS");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::managed_manifest;
+    use crate::sim_guard::{ProcessLister, SimGuardSettings};
 
-    // Create target directory if it doesn't exist
-    fs::create_dir_all(&target_dir)
-        .map_err(|e| format!("Failed to create target directory: {}", e))?;
+    struct NoProcesses;
 
-    // Check if any files will be overwritten
-    for file_path in &selected_files {
-        let source_path = PathBuf::from(file_path);
-
-        if !source_path.exists() {
-            return Err(format!("Source file does not exist: {}", file_path));
+    impl ProcessLister for NoProcesses {
+        fn running_processes(&self) -> Result<Vec<String>, String> {
+            Ok(Vec::new())
         }
     }
 
-    // Check if target directory has any actual files
-    let has_existing_files = helpers::has_actual_files(&target_dir)?;
-
-    // If there are existing files, show confirmation dialog
-    if has_existing_files {
-        // Create channel to receive dialog result
-        let (tx, rx) = std::sync::mpsc::channel();
-
-        // Show confirmation dialog using the DialogExt trait
-        app.dialog()
-            .message("This will replace existing GSX profiles. Would you like to proceed?")
-            .title("Warning")
-            .kind(tauri_plugin_dialog::MessageDialogKind::Warning)
-            .buttons(tauri_plugin_dialog::MessageDialogButtons::OkCancel)
-            .show(move |response| {
-                let _ = tx.send(response);
-            });
+    struct Fixture {
+        _dir: tempfile::TempDir,
+        library: PathBuf,
+        target: PathBuf,
+    }
 
-        // Wait for user response - true for OK, false for Cancel
-        let response = rx
-            .recv()
-            .map_err(|_| "Dialog interaction failed".to_string())?;
-        if !response {
-            return Ok("Operation cancelled by user.".to_string());
+    impl Fixture {
+        fn new(files: &[&str]) -> Fixture {
+            let dir = tempfile::tempdir().unwrap();
+            let library = dir.path().join("gsx-profiles");
+            let target = dir.path().join("GSX");
+
+            for file in files {
+                let path = library.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, *file).unwrap();
+            }
+
+            Fixture {
+                _dir: dir,
+                library,
+                target,
+            }
         }
 
-        // Only ask for backup if there are actual files to back up
-        let (backup_tx, backup_rx) = std::sync::mpsc::channel();
+        fn source(&self, file: &str) -> String {
+            self.library.join(file).to_string_lossy().to_string()
+        }
 
-        app.dialog()
-            .message("Would you like to create a backup of your current profiles?")
-            .title("Backup")
-            .buttons(tauri_plugin_dialog::MessageDialogButtons::YesNo)
-            .show(move |response| {
-                let _ = backup_tx.send(response);
-            });
+        fn activate(
+            &self,
+            files: &[&str],
+            options: &ActivationOptions,
+            answer: Option<bool>,
+        ) -> Result<ActivationReport, ActivationError> {
+            run_activation(
+                &self.target,
+                files.iter().map(|file| self.source(file)).collect(),
+                &BackendSettings::default(),
+                options,
+                &ProgressReporter::default(),
+                &SimGuard::new(Box::new(NoProcesses), SimGuardSettings::default()),
+                &mut |_| match answer {
+                    Some(proceed) => Ok(OverwriteAnswer {
+                        proceed,
+                        backup: false,
+                    }),
+                    None => panic!("unexpected prompt"),
+                },
+            )
+        }
 
-        // Wait for user response - true for Yes, false for No
-        let backup_response = backup_rx
-            .recv()
-            .map_err(|_| "Dialog interaction failed".to_string())?;
-        if backup_response {
-            helpers::create_profile_backup(&target_dir)?;
+        fn content(&self, name: &str) -> String {
+            fs::read_to_string(self.target.join(name)).unwrap()
         }
     }
 
-    // Now proceed with symlink removal and creation
-    helpers::remove_existing_symlinks(&target_dir)?;
-
-    let activated_file_count =
-        helpers::create_profile_symlinks(selected_files.clone(), &target_dir)?;
-    // Return a more accurate message that focuses on successful activation
-    if activated_file_count > 0 {
-        Ok(
-            "GSX profiles activated successfully! Your simulator is ready to use these profiles."
-                .to_string(),
-        )
-    } else {
-        Ok("No profiles were activated. Please check your selected profiles.".to_string())
+    #[test]
+    fn first_activation_links_the_selection() {
+        let fixture = Fixture::new(&["eddm/eddm-aerosoft.ini", "egll/egll-justsim.ini"]);
+
+        let report = fixture
+            .activate(
+                &["eddm/eddm-aerosoft.ini", "egll/egll-justsim.ini"],
+                &ActivationOptions::default(),
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(report.linked.len(), 2);
+        assert_eq!(report.delta.added.len(), 2);
+        assert_eq!(
+            fixture.content("eddm-aerosoft.ini"),
+            "eddm/eddm-aerosoft.ini"
+        );
+        let manifest = managed_manifest::load_manifest(&fixture.target)
+            .unwrap()
+            .unwrap();
+        assert_eq!(manifest.entries.len(), 2);
+    }
+
+    #[test]
+    fn reactivation_leaves_unchanged_links_alone() {
+        let fixture = Fixture::new(&["eddm/eddm-aerosoft.ini", "egll/egll-justsim.ini"]);
+        let options = ActivationOptions::default();
+        fixture
+            .activate(&["eddm/eddm-aerosoft.ini"], &options, None)
+            .unwrap();
+
+        let report = fixture
+            .activate(
+                &["eddm/eddm-aerosoft.ini", "egll/egll-justsim.ini"],
+                &options,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(report.delta.unchanged.len(), 1);
+        assert_eq!(report.delta.added.len(), 1);
+        assert_eq!(report.linked.len(), 1);
+        assert!(report.removed.is_empty());
+
+        let report = fixture
+            .activate(
+                &["eddm/eddm-aerosoft.ini", "egll/egll-justsim.ini"],
+                &options,
+                None,
+            )
+            .unwrap();
+        assert!(report.linked.is_empty());
+        assert_eq!(report.delta.unchanged.len(), 2);
+    }
+
+    #[test]
+    fn same_named_unmanaged_file_needs_confirmation() {
+        let fixture = Fixture::new(&["eddm/eddm-aerosoft.ini"]);
+        fs::create_dir_all(&fixture.target).unwrap();
+        fs::write(fixture.target.join("eddm-aerosoft.ini"), "mine").unwrap();
+        let selection = ["eddm/eddm-aerosoft.ini"];
+
+        // An explicit Preserve never replaces it
+        let preserve = ActivationOptions {
+            unmanaged_policy: Some(UnmanagedPolicy::Preserve),
+            ..Default::default()
+        };
+        assert!(matches!(
+            fixture.activate(&selection, &preserve, None),
+            Err(ActivationError::UnmanagedFiles { .. })
+        ));
+
+        let never = ActivationOptions {
+            overwrite: OverwritePolicy::Never,
+            ..Default::default()
+        };
+        assert!(matches!(
+            fixture.activate(&selection, &never, None),
+            Err(ActivationError::UnmanagedFiles { .. })
+        ));
+
+        let ask = ActivationOptions::default();
+        assert!(matches!(
+            fixture.activate(&selection, &ask, Some(false)),
+            Err(ActivationError::Cancelled)
+        ));
+        assert_eq!(fixture.content("eddm-aerosoft.ini"), "mine");
+
+        let report = fixture.activate(&selection, &ask, Some(true)).unwrap();
+        assert_eq!(report.linked.len(), 1);
+        assert_eq!(
+            fixture.content("eddm-aerosoft.ini"),
+            "eddm/eddm-aerosoft.ini"
+        );
+    }
+
+    #[test]
+    fn failed_activation_rolls_back() {
+        let fixture = Fixture::new(&[
+            "eddm/1.0/eddm-aerosoft.ini",
+            "eddm/2.0/eddm-aerosoft.ini",
+            "egll/egll-justsim.ini",
+        ]);
+        let options = ActivationOptions::default();
+        fixture
+            .activate(&["eddm/1.0/eddm-aerosoft.ini"], &options, None)
+            .unwrap();
+        let manifest_before = managed_manifest::load_manifest(&fixture.target).unwrap();
+
+        // A folder of the user's blocks one of the new links once the swap has started
+        fs::create_dir(fixture.target.join("egll-justsim.ini")).unwrap();
+
+        let result = fixture.activate(
+            &["eddm/2.0/eddm-aerosoft.ini", "egll/egll-justsim.ini"],
+            &options,
+            None,
+        );
+
+        assert!(matches!(result, Err(ActivationError::Link { .. })));
+        assert_eq!(
+            fixture.content("eddm-aerosoft.ini"),
+            "eddm/1.0/eddm-aerosoft.ini"
+        );
+        assert!(fixture.target.join("egll-justsim.ini").is_dir());
+        assert_eq!(
+            managed_manifest::load_manifest(&fixture.target).unwrap(),
+            manifest_before
+        );
+        assert!(!fixture.target.join(".gsx-activation-staging").exists());
+        assert!(!fixture.target.join(".gsx-activation-rollback").exists());
     }
 }
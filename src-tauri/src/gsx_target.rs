@@ -0,0 +1,112 @@
+use serde::Serialize;
+use std::env;
+use std::path::PathBuf;
+use tauri::{command, AppHandle};
+
+use crate::settings;
+
+// Set this to point activation at another folder, e.g. a portable GSX install or a temp dir
+pub const TARGET_DIR_ENV_VAR: &str = "GSX_PROFILE_MANAGER_TARGET_DIR";
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetDirSource {
+    Environment,
+    Settings,
+    Detected,
+    Default,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct GsxTargetDir {
+    pub path: String,
+    pub source: TargetDirSource,
+    pub exists: bool,
+}
+
+// Roaming AppData roots to look in. APPDATA comes first to match earlier versions;
+// the known-folder lookup also covers redirected AppData.
+fn roaming_app_data_dirs() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+
+    if let Ok(roaming_dir) = env::var("APPDATA") {
+        roots.push(PathBuf::from(roaming_dir));
+    }
+
+    if let Some(config_dir) = dirs_next::config_dir() {
+        if !roots.contains(&config_dir) {
+            roots.push(config_dir);
+        }
+    }
+
+    roots
+}
+
+/// All GSX profile folders that may be used, in order of preference.
+/// GSX for MSFS 2020 uses Virtuali\GSX\MSFS; the MSFS 2024 version uses Virtuali\GSX\MSFS2024.
+pub fn gsx_target_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+
+    for sim_folder in ["MSFS", "MSFS2024"] {
+        for root in roaming_app_data_dirs() {
+            candidates.push(root.join("Virtuali").join("GSX").join(sim_folder));
+        }
+    }
+
+    candidates
+}
+
+/// Resolve the GSX profile folder activation writes into.
+/// The environment override wins, then the folder saved in the settings, then the first
+/// candidate that exists. If none exists yet, the default MSFS folder is used and created
+/// on activation, like earlier versions did.
+pub fn resolve_gsx_target_dir(configured_dir: Option<&str>) -> Result<GsxTargetDir, String> {
+    let (path, source) =
+        if let Some(dir) = env::var_os(TARGET_DIR_ENV_VAR).filter(|d| !d.is_empty()) {
+            (PathBuf::from(dir), TargetDirSource::Environment)
+        } else if let Some(dir) = configured_dir.map(str::trim).filter(|d| !d.is_empty()) {
+            (PathBuf::from(dir), TargetDirSource::Settings)
+        } else {
+            let candidates = gsx_target_candidates();
+
+            match candidates.iter().find(|candidate| candidate.is_dir()) {
+                Some(existing) => (existing.clone(), TargetDirSource::Detected),
+                None => {
+                    let default_dir = candidates.into_iter().next().ok_or_else(|| {
+                        "Failed to locate the AppData folder for the GSX profiles".to_string()
+                    })?;
+                    (default_dir, TargetDirSource::Default)
+                }
+            }
+        };
+
+    Ok(GsxTargetDir {
+        exists: path.is_dir(),
+        path: path.to_string_lossy().to_string(),
+        source,
+    })
+}
+
+// Resolve the GSX profile folder using the saved backend settings
+pub fn get_gsx_target_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let settings = settings::load_settings(app)?;
+    let target = resolve_gsx_target_dir(settings.gsx_target_dir.as_deref())?;
+
+    Ok(PathBuf::from(target.path))
+}
+
+#[command]
+pub fn get_gsx_target_info(app: AppHandle) -> Result<GsxTargetDir, String> {
+    let settings = settings::load_settings(&app)?;
+    resolve_gsx_target_dir(settings.gsx_target_dir.as_deref())
+}
+
+// List the GSX folders found on this machine so the user can pick one in the settings
+#[command]
+pub fn detect_gsx_target_dirs() -> Vec<String> {
+    gsx_target_candidates()
+        .into_iter()
+        .filter(|candidate| candidate.is_dir())
+        .map(|candidate| candidate.to_string_lossy().to_string())
+        .collect()
+}
@@ -4,6 +4,7 @@ use std::os::windows::process::CommandExt;
 use std::process::Command;
 use tauri::AppHandle;
 
+#[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
 #[tauri::command]
@@ -71,6 +72,7 @@ pub fn restart_as_admin(app_handle: AppHandle) -> Result<(), String> {
 
     #[cfg(not(target_os = "windows"))]
     {
+        let _ = app_handle;
         Err("Admin restart is only supported on Windows".to_string())
     }
 }
@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// How a profile file from the library is made available in the GSX folder
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkStrategy {
+    // Try the platform symlink first, then a hardlink, then a plain copy
+    #[default]
+    Auto,
+    WindowsSymlink,
+    UnixSymlink,
+    Hardlink,
+    Copy,
+}
+
+impl LinkStrategy {
+    // The strategies Auto tries, in order of preference for the current platform
+    fn auto_candidates() -> Vec<LinkStrategy> {
+        if cfg!(windows) {
+            vec![
+                LinkStrategy::WindowsSymlink,
+                LinkStrategy::Hardlink,
+                LinkStrategy::Copy,
+            ]
+        } else {
+            vec![
+                LinkStrategy::UnixSymlink,
+                LinkStrategy::Hardlink,
+                LinkStrategy::Copy,
+            ]
+        }
+    }
+}
+
+/// Create `target` from `source` using the given strategy and return the strategy that was
+/// actually used. With Auto, each candidate is tried in turn until one succeeds, so a missing
+/// symlink privilege on Windows or a library on another drive still ends in a working profile.
+pub fn create_link(
+    source: &Path,
+    target: &Path,
+    strategy: LinkStrategy,
+) -> Result<LinkStrategy, String> {
+    if strategy != LinkStrategy::Auto {
+        create_link_with(source, target, strategy)?;
+        return Ok(strategy);
+    }
+
+    create_first_working(source, target, &LinkStrategy::auto_candidates())
+}
+
+// Try each strategy in turn and return the first one that worked
+fn create_first_working(
+    source: &Path,
+    target: &Path,
+    candidates: &[LinkStrategy],
+) -> Result<LinkStrategy, String> {
+    let mut errors = Vec::new();
+    for &candidate in candidates {
+        match create_link_with(source, target, candidate) {
+            Ok(()) => return Ok(candidate),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    Err(format!(
+        "Failed to link {:?} with any strategy: {}",
+        source,
+        errors.join("; ")
+    ))
+}
+
+fn create_link_with(source: &Path, target: &Path, strategy: LinkStrategy) -> Result<(), String> {
+    match strategy {
+        LinkStrategy::Auto => create_link(source, target, strategy).map(|_| ()),
+        LinkStrategy::WindowsSymlink => create_windows_symlink(source, target),
+        LinkStrategy::UnixSymlink => create_unix_symlink(source, target),
+        LinkStrategy::Hardlink => {
+            // Hardlinks only exist for files; Auto falls back to copying the folder
+            if source.is_dir() {
+                return Err(format!("Cannot hardlink folder {:?}", source));
+            }
+            fs::hard_link(source, target).map_err(|e| format!("Failed to create hardlink: {}", e))
+        }
+        LinkStrategy::Copy => {
+            if source.is_dir() {
+                return copy_dir(source, target);
+            }
+            fs::copy(source, target)
+                .map(|_| ())
+                .map_err(|e| format!("Failed to copy file: {}", e))
+        }
+    }
+}
+
+// Copy a folder with everything in it, e.g. a Python helper package
+fn copy_dir(source: &Path, target: &Path) -> Result<(), String> {
+    fs::create_dir_all(target).map_err(|e| format!("Failed to create folder: {}", e))?;
+
+    let entries = fs::read_dir(source).map_err(|e| format!("Failed to read folder: {}", e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+        let target_path = target.join(entry.file_name());
+
+        if path.is_dir() {
+            copy_dir(&path, &target_path)?;
+        } else {
+            fs::copy(&path, &target_path).map_err(|e| format!("Failed to copy file: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn create_windows_symlink(source: &Path, target: &Path) -> Result<(), String> {
+    use std::os::windows::fs as win_fs;
+
+    if source.is_dir() {
+        win_fs::symlink_dir(source, target)
+            .map_err(|e| format!("Failed to create directory symlink: {}", e))
+    } else {
+        win_fs::symlink_file(source, target)
+            .map_err(|e| format!("Failed to create file symlink: {}", e))
+    }
+}
+
+#[cfg(not(windows))]
+fn create_windows_symlink(_source: &Path, _target: &Path) -> Result<(), String> {
+    Err("Windows symlinks are only supported on Windows".to_string())
+}
+
+#[cfg(unix)]
+fn create_unix_symlink(source: &Path, target: &Path) -> Result<(), String> {
+    std::os::unix::fs::symlink(source, target)
+        .map_err(|e| format!("Failed to create symlink: {}", e))
+}
+
+#[cfg(not(unix))]
+fn create_unix_symlink(_source: &Path, _target: &Path) -> Result<(), String> {
+    Err("Unix symlinks are only supported on Unix-like systems".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn library() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("eddm-aerosoft.ini");
+        fs::write(&file, "profile").unwrap();
+
+        let folder = dir.path().join("helpers");
+        fs::create_dir_all(folder.join("nested")).unwrap();
+        fs::write(folder.join("__init__.py"), "init").unwrap();
+        fs::write(folder.join("nested").join("data.py"), "data").unwrap();
+
+        (dir, file, folder)
+    }
+
+    #[test]
+    fn auto_prefers_the_platform_symlink() {
+        let (dir, file, _) = library();
+        let target = dir.path().join("linked.ini");
+
+        let used = create_link(&file, &target, LinkStrategy::Auto).unwrap();
+
+        let expected = if cfg!(windows) {
+            LinkStrategy::WindowsSymlink
+        } else {
+            LinkStrategy::UnixSymlink
+        };
+        assert_eq!(LinkStrategy::auto_candidates()[0], expected);
+        assert_eq!(
+            &LinkStrategy::auto_candidates()[1..],
+            &[LinkStrategy::Hardlink, LinkStrategy::Copy]
+        );
+        // Symlinks may need a privilege on Windows, where Auto can end up with a hardlink
+        if !cfg!(windows) {
+            assert_eq!(used, expected);
+            assert!(target.is_symlink());
+        }
+        assert_eq!(fs::read_to_string(&target).unwrap(), "profile");
+    }
+
+    // A symlink kind the platform lacks fails, so these fall through to the next candidate
+    fn unsupported_symlink() -> LinkStrategy {
+        if cfg!(windows) {
+            LinkStrategy::UnixSymlink
+        } else {
+            LinkStrategy::WindowsSymlink
+        }
+    }
+
+    #[test]
+    fn falls_back_to_hardlink_for_files() {
+        let (dir, file, _) = library();
+        let target = dir.path().join("linked.ini");
+        let candidates = [
+            unsupported_symlink(),
+            LinkStrategy::Hardlink,
+            LinkStrategy::Copy,
+        ];
+
+        let used = create_first_working(&file, &target, &candidates).unwrap();
+
+        assert_eq!(used, LinkStrategy::Hardlink);
+        assert!(!target.is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "profile");
+    }
+
+    #[test]
+    fn falls_back_to_copy_for_folders() {
+        let (dir, _, folder) = library();
+        let target = dir.path().join("copied");
+        let candidates = [
+            unsupported_symlink(),
+            LinkStrategy::Hardlink,
+            LinkStrategy::Copy,
+        ];
+
+        let used = create_first_working(&folder, &target, &candidates).unwrap();
+
+        assert_eq!(used, LinkStrategy::Copy);
+        assert_eq!(
+            fs::read_to_string(target.join("nested").join("data.py")).unwrap(),
+            "data"
+        );
+    }
+
+    #[test]
+    fn reports_every_failed_strategy() {
+        let (dir, _, folder) = library();
+        let target = dir.path().join("linked");
+
+        let error = create_first_working(
+            &folder,
+            &target,
+            &[unsupported_symlink(), LinkStrategy::Hardlink],
+        )
+        .unwrap_err();
+
+        assert!(error.contains("only supported"), "{}", error);
+        assert!(error.contains("Cannot hardlink folder"), "{}", error);
+    }
+
+    #[test]
+    fn copy_dir_copies_nested_folders() {
+        let (dir, _, folder) = library();
+        let target = dir.path().join("copied");
+
+        copy_dir(&folder, &target).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(target.join("__init__.py")).unwrap(),
+            "init"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("nested").join("data.py")).unwrap(),
+            "data"
+        );
+        // A real copy, so changing it leaves the library alone
+        fs::write(target.join("__init__.py"), "changed").unwrap();
+        assert_eq!(
+            fs::read_to_string(folder.join("__init__.py")).unwrap(),
+            "init"
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle};
+
+use crate::activation_history;
+use crate::activation_report::{ActivationError, ActivationReport};
+use crate::create_profile_symlink::{self, ActivationOptions};
+use crate::gsx_target::get_gsx_target_dir;
+use crate::settings;
+
+const LOADOUTS_FILE_NAME: &str = "loadouts.json";
+
+/// A named set of profile files that can be activated in one go, e.g. "Europe tour"
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Loadout {
+    pub name: String,
+    pub files: Vec<String>,
+    pub created_at: u64, // Unix seconds
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+struct LoadoutStore {
+    loadouts: Vec<Loadout>,
+}
+
+/// Files that switching from one set to another would add and remove
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct LoadoutDiff {
+    pub from: String, // Loadout name, or "active" for the current GSX folder
+    pub to: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+// Name used in diffs for what is activated in the GSX folder right now
+const ACTIVE_SET_NAME: &str = "active";
+
+// Loadouts live next to the settings in the app data folder
+fn get_loadouts_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(settings::app_data_dir(app)?.join(LOADOUTS_FILE_NAME))
+}
+
+fn load_store(app: &AppHandle) -> Result<LoadoutStore, String> {
+    let path = get_loadouts_path(app)?;
+
+    if !path.exists() {
+        return Ok(LoadoutStore::default());
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read loadouts file: {}", e))?;
+
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse loadouts file: {}", e))
+}
+
+fn save_store(app: &AppHandle, store: &LoadoutStore) -> Result<(), String> {
+    let path = get_loadouts_path(app)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create loadouts directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(store)
+        .map_err(|e| format!("Failed to serialize loadouts: {}", e))?;
+
+    fs::write(&path, content).map_err(|e| format!("Failed to write loadouts file: {}", e))
+}
+
+// Loadout names are matched ignoring case and surrounding whitespace
+fn names_match(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+fn find_loadout(app: &AppHandle, name: &str) -> Result<Loadout, String> {
+    load_store(app)?
+        .loadouts
+        .into_iter()
+        .find(|loadout| names_match(&loadout.name, name))
+        .ok_or_else(|| format!("Loadout not found: {}", name))
+}
+
+fn files_for(app: &AppHandle, name: &str) -> Result<Vec<String>, String> {
+    if names_match(name, ACTIVE_SET_NAME) {
+        activation_history::active_files(&get_gsx_target_dir(app)?)
+    } else {
+        Ok(find_loadout(app, name)?.files)
+    }
+}
+
+/// Compare two file sets, keeping the order of the set each file comes from
+pub fn diff_file_sets(from: &[String], to: &[String]) -> LoadoutDiff {
+    let from_set: HashSet<&String> = from.iter().collect();
+    let to_set: HashSet<&String> = to.iter().collect();
+
+    LoadoutDiff {
+        added: to
+            .iter()
+            .filter(|file| !from_set.contains(file))
+            .cloned()
+            .collect(),
+        removed: from
+            .iter()
+            .filter(|file| !to_set.contains(file))
+            .cloned()
+            .collect(),
+        unchanged: to
+            .iter()
+            .filter(|file| from_set.contains(file))
+            .cloned()
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[command]
+pub fn list_loadouts(app: AppHandle) -> Result<Vec<Loadout>, String> {
+    Ok(load_store(&app)?.loadouts)
+}
+
+#[command]
+pub fn get_loadout(app: AppHandle, name: String) -> Result<Loadout, String> {
+    find_loadout(&app, &name)
+}
+
+/// Create a loadout, or replace the files of the loadout with the same name
+#[command]
+pub fn save_loadout(app: AppHandle, name: String, files: Vec<String>) -> Result<Loadout, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Loadout name must not be empty".to_string());
+    }
+    if names_match(&name, ACTIVE_SET_NAME) {
+        return Err(format!("\"{}\" is reserved for the active profiles", name));
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| format!("Failed to get system time: {}", e))?
+        .as_secs();
+
+    // Drop duplicates but keep the selection order, which decides conflicts
+    let mut seen = HashSet::new();
+    let files: Vec<String> = files
+        .into_iter()
+        .filter(|file| seen.insert(file.clone()))
+        .collect();
+
+    let mut store = load_store(&app)?;
+    let loadout = match store
+        .loadouts
+        .iter_mut()
+        .find(|loadout| names_match(&loadout.name, &name))
+    {
+        Some(loadout) => {
+            loadout.name = name;
+            loadout.files = files;
+            loadout.updated_at = now;
+            loadout.clone()
+        }
+        None => {
+            let loadout = Loadout {
+                name,
+                files,
+                created_at: now,
+                updated_at: now,
+            };
+            store.loadouts.push(loadout.clone());
+            loadout
+        }
+    };
+
+    save_store(&app, &store)?;
+    println!(
+        "Saved loadout {} with {} files",
+        loadout.name,
+        loadout.files.len()
+    );
+
+    Ok(loadout)
+}
+
+/// Save what is activated in the GSX folder right now as a loadout
+#[command]
+pub fn save_active_as_loadout(app: AppHandle, name: String) -> Result<Loadout, String> {
+    let files = activation_history::active_files(&get_gsx_target_dir(&app)?)?;
+    save_loadout(app, name, files)
+}
+
+#[command]
+pub fn delete_loadout(app: AppHandle, name: String) -> Result<(), String> {
+    let mut store = load_store(&app)?;
+    let count = store.loadouts.len();
+
+    store
+        .loadouts
+        .retain(|loadout| !names_match(&loadout.name, &name));
+    if store.loadouts.len() == count {
+        return Err(format!("Loadout not found: {}", name));
+    }
+
+    save_store(&app, &store)
+}
+
+/// Diff two loadouts. Either name may be "active" for what is in the GSX folder right now;
+/// `from` defaults to it.
+#[command]
+pub fn diff_loadouts(
+    app: AppHandle,
+    from: Option<String>,
+    to: String,
+) -> Result<LoadoutDiff, String> {
+    let from = from.unwrap_or_else(|| ACTIVE_SET_NAME.to_string());
+
+    let mut diff = diff_file_sets(&files_for(&app, &from)?, &files_for(&app, &to)?);
+    diff.from = from;
+    diff.to = to;
+
+    Ok(diff)
+}
+
+/// Activate a loadout by name, exactly like activate_profiles with its files
+#[command]
+pub async fn activate_loadout(
+    app: AppHandle,
+    name: String,
+    options: Option<ActivationOptions>,
+) -> Result<ActivationReport, ActivationError> {
+    let loadout =
+        find_loadout(&app, &name).map_err(|message| ActivationError::Loadout { message })?;
+
+    println!(
+        "Activating loadout {} ({} files)",
+        loadout.name,
+        loadout.files.len()
+    );
+
+    create_profile_symlink::activate_profiles(app, loadout.files, options).await
+}
@@ -2,13 +2,36 @@
     all(not(debug_assertions), target_os = "windows"),
     windows_subsystem = "windows"
 )]
+mod activation_diff;
+mod activation_health;
+mod activation_history;
+mod activation_plan;
+mod activation_progress;
+mod activation_report;
+mod activation_transaction;
+mod backup_archive;
+mod backup_store;
+mod bgl_reader;
 mod create_profile_symlink;
 mod db;
+mod gsx_target;
 
 use tauri::Manager;
 use tauri_plugin_sql::Builder;
 mod airport_community_scanner;
 mod is_admin;
+mod link_strategy;
+mod loadouts;
+mod managed_manifest;
+mod msfs_usercfg;
+mod package_manifest;
+mod profile_adoption;
+mod profile_backups;
+mod profile_conflicts;
+mod profile_metadata;
+mod route_activation;
+mod settings;
+mod sim_guard;
 mod zip_handler;
 
 #[tauri::command]
@@ -50,18 +73,58 @@ fn main() {
         .plugin(tauri_plugin_fs::init())
         .plugin(Builder::default().build())
         .plugin(tauri_plugin_dialog::init())
+        .manage(activation_progress::ActivationCancellation::default())
         .invoke_handler(tauri::generate_handler![
             create_profile_symlink::activate_profiles,
+            activation_progress::cancel_activation,
+            activation_plan::plan_profile_activation,
             is_admin::is_admin,
             is_admin::restart_as_admin,
             airport_community_scanner::scan_for_airport_scenery,
+            airport_community_scanner::scan_airport_packages,
+            airport_community_scanner::list_scan_roots,
+            airport_community_scanner::add_scan_root,
+            airport_community_scanner::remove_scan_root,
             zip_handler::extract_zip_file,
+            profile_backups::list_profile_backups,
+            profile_backups::get_profile_backup,
+            profile_backups::restore_profile_backup,
+            profile_backups::delete_profile_backup,
+            profile_backups::prune_profile_backups,
+            settings::get_backend_settings,
+            settings::update_backend_settings,
+            gsx_target::get_gsx_target_info,
+            gsx_target::detect_gsx_target_dirs,
+            profile_conflicts::detect_profile_conflicts,
+            profile_adoption::scan_gsx_folder_for_adoption,
+            profile_adoption::adopt_gsx_profiles,
+            profile_adoption::list_profile_adoptions,
+            profile_adoption::undo_profile_adoption,
+            activation_health::check_gsx_health,
+            activation_health::repair_gsx_folder,
+            activation_history::list_activation_history,
+            activation_history::undo_activation,
+            loadouts::list_loadouts,
+            loadouts::get_loadout,
+            loadouts::save_loadout,
+            loadouts::save_active_as_loadout,
+            loadouts::delete_loadout,
+            loadouts::diff_loadouts,
+            loadouts::activate_loadout,
+            route_activation::match_route_profiles,
+            route_activation::activate_route,
             switch_to_main_window,
         ])
-        .setup(|_app| {
+        .setup(|app| {
             // Initialize the database.
             db::init();
 
+            // Roll back an activation that was interrupted on the last run.
+            activation_transaction::recover_interrupted_activation(app.handle());
+
+            // Report links that broke or changed since the last activation.
+            activation_health::check_health_on_startup(app.handle());
+
             Ok(())
         })
         .run(tauri::generate_context!())
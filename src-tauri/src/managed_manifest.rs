@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Folder inside the GSX target directory holding the manifest. Like the other ".gsx-"
+// folders, the helpers never back it up, link over it or remove it.
+pub const MANIFEST_DIR_NAME: &str = ".gsx-profile-manager";
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// What to do with files in the GSX folder that the manager did not create
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmanagedPolicy {
+    // Never touch them. A selected profile with the same name fails the activation, unless
+    // the caller left the policy out and the overwrite policy allows replacing that file.
+    #[default]
+    Preserve,
+    // Replace only the ones a selected profile has the same name as
+    Replace,
+    // Take ownership of all of them, so they are replaced or removed like managed entries
+    Adopt,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ManagedEntry {
+    pub name: String, // File name in the GSX folder
+    pub source: String,
+}
+
+/// The entries in the GSX folder that were created by the manager
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct ManagedManifest {
+    pub entries: Vec<ManagedEntry>,
+}
+
+impl ManagedManifest {
+    pub fn is_managed(&self, name: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| names_match(&entry.name, name))
+    }
+
+    // Add an entry, replacing any previous entry with the same name
+    pub fn insert(&mut self, entry: ManagedEntry) {
+        self.remove(&entry.name);
+        self.entries.push(entry);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ManagedEntry> {
+        self.entries
+            .iter()
+            .find(|entry| names_match(&entry.name, name))
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.entries.retain(|entry| !names_match(&entry.name, name));
+    }
+}
+
+/// File names are case-insensitive on Windows, so "EDDM.ini" and "eddm.ini" are the same
+/// entry. Everything that compares entry names goes through this key.
+pub fn name_key(name: &str) -> String {
+    if cfg!(windows) {
+        name.to_lowercase()
+    } else {
+        name.to_string()
+    }
+}
+
+fn names_match(a: &str, b: &str) -> bool {
+    name_key(a) == name_key(b)
+}
+
+pub fn manifest_dir(target_dir: &Path) -> PathBuf {
+    target_dir.join(MANIFEST_DIR_NAME)
+}
+
+pub fn manifest_path(target_dir: &Path) -> PathBuf {
+    manifest_dir(target_dir).join(MANIFEST_FILE_NAME)
+}
+
+pub fn load_manifest(target_dir: &Path) -> Result<Option<ManagedManifest>, String> {
+    let path = manifest_path(target_dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read manifest: {}", e))?;
+    let manifest =
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse manifest: {}", e))?;
+
+    Ok(Some(manifest))
+}
+
+/// Load the manifest, or build one from the symlinks in the GSX folder when it was last
+/// activated before the manifest existed (see Ownership::is_managed)
+pub fn load_or_infer_manifest(target_dir: &Path) -> Result<ManagedManifest, String> {
+    if let Some(manifest) = load_manifest(target_dir)? {
+        return Ok(manifest);
+    }
+
+    let mut manifest = ManagedManifest::default();
+    if !target_dir.exists() {
+        return Ok(manifest);
+    }
+
+    let entries =
+        fs::read_dir(target_dir).map_err(|e| format!("Failed to read target directory: {}", e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+
+        if path.is_symlink() {
+            let source = fs::read_link(&path)
+                .map(|source| source.to_string_lossy().to_string())
+                .unwrap_or_default();
+            manifest.insert(ManagedEntry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                source,
+            });
+        }
+    }
+
+    Ok(manifest)
+}
+
+pub fn save_manifest(target_dir: &Path, manifest: &ManagedManifest) -> Result<(), String> {
+    fs::create_dir_all(manifest_dir(target_dir))
+        .map_err(|e| format!("Failed to create manifest directory: {}", e))?;
+
+    let content = serde_json::to_string_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+
+    fs::write(manifest_path(target_dir), content)
+        .map_err(|e| format!("Failed to write manifest: {}", e))
+}
+
+/// Decides which entries in the GSX folder belong to the manager
+pub struct Ownership {
+    manifest: Option<ManagedManifest>,
+    policy: UnmanagedPolicy,
+}
+
+impl Ownership {
+    pub fn load(target_dir: &Path, policy: UnmanagedPolicy) -> Result<Ownership, String> {
+        Ok(Ownership {
+            manifest: load_manifest(target_dir)?,
+            policy,
+        })
+    }
+
+    pub fn policy(&self) -> UnmanagedPolicy {
+        self.policy
+    }
+
+    /// Check whether an entry may be replaced or removed: it is managed, or the policy
+    /// adopts every unmanaged entry
+    pub fn owns(&self, path: &Path) -> bool {
+        self.policy == UnmanagedPolicy::Adopt || self.is_managed(path)
+    }
+
+    /// Check whether an entry was created by the manager. Folders activated before the
+    /// manifest existed only ever had symlinks created by the manager, so without a manifest
+    /// every symlink counts as managed and every regular file as the user's.
+    pub fn is_managed(&self, path: &Path) -> bool {
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => return false,
+        };
+
+        match &self.manifest {
+            Some(manifest) => manifest.is_managed(&name),
+            None => path.is_symlink(),
+        }
+    }
+
+    /// Check whether a real folder was copied there by the manager for a selected folder.
+    /// A user folder that only has the name of a managed file is not managed.
+    pub fn is_managed_folder(&self, path: &Path) -> bool {
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => return false,
+        };
+
+        path.is_dir()
+            && !path.is_symlink()
+            && self
+                .manifest
+                .as_ref()
+                .and_then(|manifest| manifest.get(&name))
+                .is_some_and(|entry| !Path::new(&entry.source).is_file())
+    }
+}
@@ -0,0 +1,215 @@
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The MSFS editions whose UserCfg.opt is looked up
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MsfsVariant {
+    Msfs2020Store,
+    Msfs2020Steam,
+    Msfs2024Store,
+    Msfs2024Steam,
+}
+
+/// An MSFS installation found through its UserCfg.opt
+#[derive(Debug, Serialize, Clone)]
+pub struct MsfsInstall {
+    pub variant: MsfsVariant,
+    pub user_cfg: PathBuf,
+    pub installed_packages_path: PathBuf,
+}
+
+// Package folders below InstalledPackagesPath, by the folder type the scanner reports
+const PACKAGE_FOLDERS: [&str; 3] = ["Community", "Official", "StreamedPackages"];
+
+/// Where each edition keeps its UserCfg.opt. Store editions use the package's LocalCache,
+/// Steam editions the roaming app data folder.
+pub fn user_cfg_candidates() -> Vec<(MsfsVariant, PathBuf)> {
+    let mut candidates = Vec::new();
+
+    if let Some(local_app_data) = dirs_next::data_local_dir() {
+        let packages = local_app_data.join("Packages");
+        candidates.push((
+            MsfsVariant::Msfs2020Store,
+            packages
+                .join("Microsoft.FlightSimulator_8wekyb3d8bbwe")
+                .join("LocalCache")
+                .join("UserCfg.opt"),
+        ));
+        candidates.push((
+            MsfsVariant::Msfs2024Store,
+            packages
+                .join("Microsoft.Limitless_8wekyb3d8bbwe")
+                .join("LocalCache")
+                .join("UserCfg.opt"),
+        ));
+    }
+
+    if let Some(app_data) = dirs_next::config_dir() {
+        candidates.push((
+            MsfsVariant::Msfs2020Steam,
+            app_data
+                .join("Microsoft Flight Simulator")
+                .join("UserCfg.opt"),
+        ));
+        candidates.push((
+            MsfsVariant::Msfs2024Steam,
+            app_data
+                .join("Microsoft Flight Simulator 2024")
+                .join("UserCfg.opt"),
+        ));
+    }
+
+    candidates
+}
+
+fn installed_packages_path_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r#"(?mi)^\s*InstalledPackagesPath\s+"([^"]+)"\s*$"#).expect("valid regex")
+    })
+}
+
+/// Read InstalledPackagesPath from the contents of a UserCfg.opt, e.g.
+/// `InstalledPackagesPath "D:\MSFS\Packages"`
+pub fn parse_installed_packages_path(content: &str) -> Option<PathBuf> {
+    installed_packages_path_regex()
+        .captures(content)?
+        .get(1)
+        .map(|path| path.as_str().trim())
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+pub fn read_user_cfg(variant: MsfsVariant, user_cfg: &Path) -> Result<MsfsInstall, String> {
+    let content =
+        fs::read(user_cfg).map_err(|e| format!("Failed to read {:?}: {}", user_cfg, e))?;
+
+    let installed_packages_path = parse_installed_packages_path(&String::from_utf8_lossy(&content))
+        .ok_or_else(|| format!("No InstalledPackagesPath in {:?}", user_cfg))?;
+
+    Ok(MsfsInstall {
+        variant,
+        user_cfg: user_cfg.to_path_buf(),
+        installed_packages_path,
+    })
+}
+
+/// The installations whose UserCfg.opt exists and names a packages folder
+pub fn find_installs(candidates: &[(MsfsVariant, PathBuf)]) -> Vec<MsfsInstall> {
+    candidates
+        .iter()
+        .filter(|(_, user_cfg)| user_cfg.exists())
+        .filter_map(
+            |(variant, user_cfg)| match read_user_cfg(*variant, user_cfg) {
+                Ok(install) => Some(install),
+                Err(e) => {
+                    println!("Skipping {:?}: {}", user_cfg, e);
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+/// The Community, Official and StreamedPackages folders that exist below a packages folder,
+/// with their folder type
+pub fn package_folders(installed_packages_path: &Path) -> Vec<(PathBuf, String)> {
+    PACKAGE_FOLDERS
+        .iter()
+        .map(|name| (installed_packages_path.join(name), name.to_string()))
+        .filter(|(path, _)| path.is_dir())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/usercfg")
+            .join(name)
+    }
+
+    #[test]
+    fn parses_quoted_path_with_spaces() {
+        assert_eq!(
+            parse_installed_packages_path("InstalledPackagesPath \"E:\\MSFS 2024\\Packages\"\n"),
+            Some(PathBuf::from("E:\\MSFS 2024\\Packages"))
+        );
+        // Indented, any case, trailing blanks
+        assert_eq!(
+            parse_installed_packages_path("  installedpackagespath   \"D:\\Packages\"  "),
+            Some(PathBuf::from("D:\\Packages"))
+        );
+    }
+
+    #[test]
+    fn ignores_missing_unquoted_and_empty_paths() {
+        assert_eq!(parse_installed_packages_path("Version 64\n"), None);
+        assert_eq!(
+            parse_installed_packages_path("InstalledPackagesPath D:\\Packages\n"),
+            None
+        );
+        assert_eq!(
+            parse_installed_packages_path("InstalledPackagesPath \"  \"\n"),
+            None
+        );
+        // Only a line of its own counts
+        assert_eq!(
+            parse_installed_packages_path("OldInstalledPackagesPath \"D:\\Old\"\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn reads_crlf_user_cfg_fixture() {
+        let install =
+            read_user_cfg(MsfsVariant::Msfs2020Steam, &fixture("UserCfg-crlf.opt")).unwrap();
+
+        assert_eq!(install.variant, MsfsVariant::Msfs2020Steam);
+        assert_eq!(
+            install.installed_packages_path,
+            PathBuf::from("D:\\Flight Sim\\MSFS Packages")
+        );
+    }
+
+    #[test]
+    fn skips_user_cfg_without_packages_path() {
+        let missing = fixture("UserCfg-no-packages-path.opt");
+        assert!(read_user_cfg(MsfsVariant::Msfs2024Store, &missing).is_err());
+
+        let installs = find_installs(&[
+            (MsfsVariant::Msfs2024Store, missing),
+            (MsfsVariant::Msfs2024Steam, fixture("does-not-exist.opt")),
+            (MsfsVariant::Msfs2020Store, fixture("UserCfg-crlf.opt")),
+        ]);
+        assert_eq!(installs.len(), 1);
+        assert_eq!(installs[0].variant, MsfsVariant::Msfs2020Store);
+    }
+
+    #[test]
+    fn lists_existing_package_folders() {
+        let packages = tempfile::tempdir().unwrap();
+        fs::create_dir(packages.path().join("Community")).unwrap();
+        fs::create_dir(packages.path().join("StreamedPackages")).unwrap();
+        // A file of that name is no package folder
+        fs::write(packages.path().join("Official"), "").unwrap();
+
+        assert_eq!(
+            package_folders(packages.path()),
+            vec![
+                (packages.path().join("Community"), "Community".to_string()),
+                (
+                    packages.path().join("StreamedPackages"),
+                    "StreamedPackages".to_string()
+                ),
+            ]
+        );
+        assert!(package_folders(&packages.path().join("missing")).is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+// The content type MSFS gives airports and other scenery packages
+const SCENERY_CONTENT_TYPE: &str = "SCENERY";
+
+// Generic four letter words of package names and titles. Developer names are not listed:
+// they are told apart by their place in the folder name and the manifest's creator.
+const NOT_ICAO: [&str; 14] = [
+    "FS20", "FS24", "MSFS", "PACK", "MESH", "CITY", "LODS", "BASE", "CORE", "DATA", "FREE", "LITE",
+    "DEMO", "TEST",
+];
+
+/// The fields of an MSFS package's manifest.json the scanner uses
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PackageManifest {
+    pub content_type: String, // "SCENERY" for airports
+    pub title: String,
+    pub creator: String,
+    pub package_version: String,
+    pub minimum_game_version: String,
+}
+
+impl PackageManifest {
+    pub fn is_scenery(&self) -> bool {
+        self.content_type
+            .trim()
+            .eq_ignore_ascii_case(SCENERY_CONTENT_TYPE)
+    }
+}
+
+/// Read the manifest.json of a package folder. None when the folder is no package.
+pub fn read_package_manifest(package_dir: &Path) -> Result<Option<PackageManifest>, String> {
+    let path = package_dir.join(MANIFEST_FILE_NAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let content = fs::read(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+
+    // Some packages are saved with a byte order mark, which serde_json rejects
+    let content = String::from_utf8_lossy(&content);
+    let content = content.trim_start_matches('\u{feff}');
+
+    serde_json::from_str(content)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse {:?}: {}", path, e))
+}
+
+// "fs20-aerosoft-airport-eddb-berlin" names the ICAO explicitly
+fn airport_folder_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"(?i)-airport-([A-Z0-9]{4})\b").expect("valid regex"))
+}
+
+fn word_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"[A-Za-z0-9]+").expect("valid regex"))
+}
+
+// An ICAO-like word starts with a letter and is neither a generic word nor the creator
+fn as_icao(word: &str, creator: &str) -> Option<String> {
+    let word = word.to_uppercase();
+    let is_icao = word.len() == 4
+        && word.starts_with(|c: char| c.is_ascii_alphabetic())
+        && word.chars().all(|c| c.is_ascii_alphanumeric())
+        && !NOT_ICAO.contains(&word.as_str())
+        && !word_regex()
+            .find_iter(creator)
+            .any(|creator_word| creator_word.as_str().eq_ignore_ascii_case(&word));
+
+    is_icao.then_some(word)
+}
+
+/// The ICAOs of a folder named like "...-airport-xxxx-...", upper case. Bundles can name
+/// several, e.g. "...-airport-lepa-airport-lesj".
+pub fn airport_folder_icaos(folder_name: &str) -> Vec<String> {
+    let mut icaos: Vec<String> = Vec::new();
+
+    for cap in airport_folder_regex().captures_iter(folder_name) {
+        let icao = cap[1].to_uppercase();
+        if !icaos.contains(&icao) {
+            icaos.push(icao);
+        }
+    }
+
+    icaos
+}
+
+/// Guess a scenery package's ICAO from its names, for packages whose scenery can't be read:
+/// an explicit "-airport-xxxx-" folder name first, then an upper case word of the title
+/// ("FlyTampa Barcelona LEBL"), then a four letter word of the folder name after the leading
+/// developer segment ("flytampa-lebl", "aerosoft-lowi-innsbruck").
+pub fn guess_icao(folder_name: &str, manifest: &PackageManifest) -> Option<String> {
+    if let Some(icao) = airport_folder_icaos(folder_name).into_iter().next() {
+        return Some(icao);
+    }
+
+    let from_title = word_regex()
+        .find_iter(&manifest.title)
+        .map(|word| word.as_str())
+        .filter(|word| word.chars().all(|c| !c.is_ascii_lowercase()))
+        .find_map(|word| as_icao(word, &manifest.creator));
+
+    from_title.or_else(|| {
+        let after_developer = folder_name.split_once('-').map_or("", |(_, rest)| rest);
+        word_regex()
+            .find_iter(after_developer)
+            .find_map(|word| as_icao(word.as_str(), &manifest.creator))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(title: &str, creator: &str) -> PackageManifest {
+        PackageManifest {
+            content_type: SCENERY_CONTENT_TYPE.to_string(),
+            title: title.to_string(),
+            creator: creator.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn prefers_explicit_airport_folder_names() {
+        assert_eq!(
+            airport_folder_icaos("fs20-airport-lepa-airport-lesj-mallorca"),
+            vec!["LEPA", "LESJ"]
+        );
+        assert_eq!(
+            guess_icao("aerosoft-airport-eddb-berlin", &manifest("Berlin EDDM", "")),
+            Some("EDDB".to_string())
+        );
+    }
+
+    #[test]
+    fn guesses_from_title_then_folder_words() {
+        assert_eq!(
+            guess_icao(
+                "flytampa-barcelona",
+                &manifest("FlyTampa Barcelona LEBL", "FlyTampa")
+            ),
+            Some("LEBL".to_string())
+        );
+        assert_eq!(
+            guess_icao(
+                "aerosoft-lowi-innsbruck",
+                &manifest("Innsbruck", "Aerosoft")
+            ),
+            Some("LOWI".to_string())
+        );
+    }
+
+    #[test]
+    fn never_takes_the_developer_for_an_airport() {
+        // The leading segment is the developer, whatever it looks like
+        assert_eq!(
+            guess_icao("orbx-landmarks-pack", &manifest("Landmarks", "")),
+            None
+        );
+        // Nor does the creator count when it shows up in the title or later in the name
+        assert_eq!(
+            guess_icao("fs20-orbx-scenery", &manifest("ORBX MSFS Scenery", "Orbx")),
+            None
+        );
+    }
+}
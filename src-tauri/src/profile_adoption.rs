@@ -0,0 +1,693 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle};
+
+use crate::activation_diff::entry_matches_source;
+use crate::activation_report::SkippedSource;
+use crate::activation_transaction;
+use crate::gsx_target::get_gsx_target_dir;
+use crate::link_strategy::{self, LinkStrategy};
+use crate::managed_manifest::{self, ManagedEntry, Ownership, UnmanagedPolicy, MANIFEST_DIR_NAME};
+use crate::profile_metadata::{get_library_root, ProfileFileInfo, PROFILE_LIBRARY_FOLDER};
+use crate::settings;
+
+// Adoption records live in the app data folder, one JSON file per adoption. In Copy mode
+// the originals go into a folder of the same name inside the manager's folder in the GSX
+// directory, out of reach of the backup list and its pruning.
+const ADOPTIONS_DIR_NAME: &str = "adoptions";
+
+// Adopted files have no continent or country, so they go under placeholder folders that
+// keep the library layout parse_library_path expects
+const UNKNOWN_CONTINENT: &str = "Unknown";
+const UNKNOWN_COUNTRY: &str = "unknown";
+
+/// How adopted files get into the library
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AdoptionMode {
+    // Move the files into the library
+    #[default]
+    Move,
+    // Copy the files into the library and keep the originals for undo
+    Copy,
+}
+
+/// Files in the GSX folder that belong to the same airport and developer
+#[derive(Debug, Serialize, Clone)]
+pub struct AdoptionGroup {
+    pub icao: String,
+    pub developer: Option<String>,
+    pub library_dir: String, // Profile folder the files would be adopted into
+    pub files: Vec<String>,  // Paths in the GSX folder
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct AdoptionScan {
+    pub target_dir: String,
+    pub groups: Vec<AdoptionGroup>,
+    pub skipped: Vec<SkippedSource>, // Unmanaged files that can't be adopted
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AdoptedFile {
+    pub original: String, // Path in the GSX folder, now a link
+    pub library: String,  // Path in the profile library
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AdoptedGroup {
+    pub icao: String,
+    pub developer: Option<String>,
+    pub library_dir: String,
+    pub files: Vec<AdoptedFile>,
+}
+
+/// Everything needed to undo an adoption
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AdoptionRecord {
+    pub id: String, // e.g. "adoption-1712345678901"
+    pub timestamp: u64,
+    pub target_dir: String,
+    pub mode: AdoptionMode,
+    pub backup_dir: Option<String>, // Where Copy mode keeps the originals
+    pub groups: Vec<AdoptedGroup>,
+}
+
+/// GSX profiles are .ini files, with an optional .py helper next to them
+pub fn is_profile_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    name.ends_with(".ini") || name.ends_with(".py")
+}
+
+fn library_dir_for(library_root: &Path, icao: &str, developer: Option<&str>) -> PathBuf {
+    let mut dir = library_root
+        .join(UNKNOWN_CONTINENT)
+        .join(UNKNOWN_COUNTRY)
+        .join(icao.to_lowercase());
+    if let Some(developer) = developer {
+        dir = dir.join(developer);
+    }
+    dir
+}
+
+/// Group the unmanaged profile files in the GSX folder by ICAO and developer, as read from
+/// their file names. Links created by the manager and backup folders are left out.
+pub fn scan_for_adoption(target_dir: &Path, library_root: &Path) -> Result<AdoptionScan, String> {
+    let mut scan = AdoptionScan {
+        target_dir: target_dir.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+
+    if !target_dir.exists() {
+        return Ok(scan);
+    }
+
+    let ownership = Ownership::load(target_dir, UnmanagedPolicy::Preserve)?;
+    let mut groups: BTreeMap<(String, Option<String>), Vec<PathBuf>> = BTreeMap::new();
+
+    let entries =
+        fs::read_dir(target_dir).map_err(|e| format!("Failed to read target directory: {}", e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+
+        // Only real files the user put there by hand
+        if !path.is_file() || path.is_symlink() || ownership.is_managed(&path) {
+            continue;
+        }
+
+        let source = path.to_string_lossy().to_string();
+        if !is_profile_file(&path) {
+            scan.skipped.push(SkippedSource {
+                source,
+                reason: "Not a GSX profile file".to_string(),
+            });
+            continue;
+        }
+
+        let info = ProfileFileInfo::from_path(&path);
+        match info.icao {
+            Some(icao) => groups.entry((icao, info.developer)).or_default().push(path),
+            None => scan.skipped.push(SkippedSource {
+                source,
+                reason: "No ICAO code in the file name".to_string(),
+            }),
+        }
+    }
+
+    for ((icao, developer), mut files) in groups {
+        files.sort();
+        scan.groups.push(AdoptionGroup {
+            library_dir: library_dir_for(library_root, &icao, developer.as_deref())
+                .to_string_lossy()
+                .to_string(),
+            icao,
+            developer,
+            files: files
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect(),
+        });
+    }
+
+    Ok(scan)
+}
+
+// Rename a file, falling back to copy and delete when the library is on another drive
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+// Where Copy mode keeps the originals of an adoption
+fn originals_dir(target_dir: &Path, adoption_id: &str) -> PathBuf {
+    target_dir
+        .join(MANIFEST_DIR_NAME)
+        .join(ADOPTIONS_DIR_NAME)
+        .join(adoption_id)
+}
+
+fn backup_path_for(backup_dir: Option<&str>, original: &Path) -> Option<PathBuf> {
+    Some(Path::new(backup_dir?).join(original.file_name()?))
+}
+
+fn adopt_file(
+    file: &AdoptedFile,
+    mode: AdoptionMode,
+    backup_dir: Option<&str>,
+    strategy: LinkStrategy,
+) -> Result<(), String> {
+    let original = Path::new(&file.original);
+    let library = Path::new(&file.library);
+
+    if let Some(parent) = library.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create profile folder {:?}: {}", parent, e))?;
+    }
+
+    match mode {
+        AdoptionMode::Move => {
+            move_file(original, library)
+                .map_err(|e| format!("Failed to move {:?} into the library: {}", original, e))?;
+        }
+        AdoptionMode::Copy => {
+            let backup = backup_path_for(backup_dir, original)
+                .ok_or_else(|| format!("No backup location for {:?}", original))?;
+            fs::rename(original, &backup)
+                .map_err(|e| format!("Failed to back up {:?}: {}", original, e))?;
+            fs::copy(&backup, library)
+                .map_err(|e| format!("Failed to copy {:?} into the library: {}", original, e))?;
+        }
+    }
+
+    link_strategy::create_link(library, original, strategy)?;
+
+    Ok(())
+}
+
+// Put an adopted file back where it was. Safe to call on files that were only partly
+// adopted: whatever step was not reached is skipped.
+fn restore_file(
+    file: &AdoptedFile,
+    mode: AdoptionMode,
+    backup_dir: Option<&str>,
+) -> Result<(), String> {
+    let original = Path::new(&file.original);
+    let library = Path::new(&file.library);
+
+    let restore_from = match mode {
+        AdoptionMode::Move => library.to_path_buf(),
+        AdoptionMode::Copy => match backup_path_for(backup_dir, original) {
+            Some(backup) => backup,
+            None => return Ok(()),
+        },
+    };
+
+    if !restore_from.exists() {
+        // Never adopted, or the library file has been deleted since
+        println!("Nothing to restore for {:?}", original);
+        return Ok(());
+    }
+
+    // Remove the link, but never something the user put there afterwards
+    if original.exists() || original.is_symlink() {
+        if !entry_matches_source(original, library)
+            && !entry_matches_source(original, &restore_from)
+        {
+            return Err(format!(
+                "{:?} has changed since it was adopted, not restoring it",
+                original
+            ));
+        }
+        fs::remove_file(original)
+            .map_err(|e| format!("Failed to remove link {:?}: {}", original, e))?;
+    }
+
+    move_file(&restore_from, original)
+        .map_err(|e| format!("Failed to restore {:?}: {}", original, e))?;
+
+    if mode == AdoptionMode::Copy && library.exists() {
+        fs::remove_file(library)
+            .map_err(|e| format!("Failed to remove library copy {:?}: {}", library, e))?;
+    }
+
+    Ok(())
+}
+
+// Remove profile folders left empty by an undo, up to the library folder
+fn remove_empty_library_dirs(dir: &Path) {
+    let mut current = Some(dir);
+
+    while let Some(dir) = current {
+        let is_library_root = dir
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy() == PROFILE_LIBRARY_FOLDER);
+        if is_library_root || fs::remove_dir(dir).is_err() {
+            break;
+        }
+        current = dir.parent();
+    }
+}
+
+fn restore_files(record: &AdoptionRecord, files: &[&AdoptedFile]) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    for file in files.iter().rev() {
+        if let Err(e) = restore_file(file, record.mode, record.backup_dir.as_deref()) {
+            errors.push(e);
+        }
+    }
+
+    for group in &record.groups {
+        remove_empty_library_dirs(Path::new(&group.library_dir));
+    }
+    if let Some(backup_dir) = &record.backup_dir {
+        // Only succeeds once they are empty
+        let backup_dir = Path::new(backup_dir);
+        let _ = fs::remove_dir(backup_dir);
+        if let Some(adoptions_dir) = backup_dir.parent() {
+            let _ = fs::remove_dir(adoptions_dir);
+        }
+    }
+
+    errors
+}
+
+/// Move or copy the scanned files into the library and replace them with links.
+/// Either every file is adopted or, after a failure, every file is put back.
+pub fn adopt_profiles(
+    scan: &AdoptionScan,
+    target_dir: &Path,
+    mode: AdoptionMode,
+    strategy: LinkStrategy,
+) -> Result<AdoptionRecord, String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| format!("Failed to get system time: {}", e))?;
+
+    let mut record = AdoptionRecord {
+        id: format!("adoption-{}", now.as_millis()),
+        timestamp: now.as_secs(),
+        target_dir: target_dir.to_string_lossy().to_string(),
+        mode,
+        backup_dir: None,
+        groups: Vec::new(),
+    };
+
+    for group in &scan.groups {
+        let library_dir = Path::new(&group.library_dir);
+        let mut files = Vec::new();
+
+        for original in &group.files {
+            let original = Path::new(original);
+            let file_name = original
+                .file_name()
+                .ok_or_else(|| format!("Could not get file name from path: {:?}", original))?;
+            let library = library_dir.join(file_name);
+
+            // Never overwrite a profile that is already in the library
+            if library.exists() {
+                return Err(format!("{:?} is already in the profile library", library));
+            }
+
+            files.push(AdoptedFile {
+                original: original.to_string_lossy().to_string(),
+                library: library.to_string_lossy().to_string(),
+            });
+        }
+
+        record.groups.push(AdoptedGroup {
+            icao: group.icao.clone(),
+            developer: group.developer.clone(),
+            library_dir: group.library_dir.clone(),
+            files,
+        });
+    }
+
+    if mode == AdoptionMode::Copy {
+        let backup_dir = originals_dir(target_dir, &record.id);
+        if backup_dir.exists() {
+            return Err(format!("Adoption {} already exists", record.id));
+        }
+        fs::create_dir_all(&backup_dir)
+            .map_err(|e| format!("Failed to create folder for the originals: {}", e))?;
+        record.backup_dir = Some(backup_dir.to_string_lossy().to_string());
+    }
+
+    let all_files: Vec<&AdoptedFile> = record
+        .groups
+        .iter()
+        .flat_map(|group| &group.files)
+        .collect();
+
+    for (index, file) in all_files.iter().enumerate() {
+        println!("Adopting {} into {}", file.original, file.library);
+
+        if let Err(e) = adopt_file(file, mode, record.backup_dir.as_deref(), strategy) {
+            let errors = restore_files(&record, &all_files[..=index]);
+            if errors.is_empty() {
+                return Err(format!("{}. The GSX folder has been restored.", e));
+            }
+            return Err(format!(
+                "{}. Restoring the GSX folder also failed: {}",
+                e,
+                errors.join("; ")
+            ));
+        }
+    }
+
+    // The links now belong to the manager
+    let mut manifest = managed_manifest::load_or_infer_manifest(target_dir)?;
+    for file in &all_files {
+        if let Some(name) = Path::new(&file.original).file_name() {
+            manifest.insert(ManagedEntry {
+                name: name.to_string_lossy().to_string(),
+                source: file.library.clone(),
+            });
+        }
+    }
+    managed_manifest::save_manifest(target_dir, &manifest)?;
+
+    Ok(record)
+}
+
+/// Put every file of an adoption back into the GSX folder and drop its library copies
+pub fn undo_adoption(record: &AdoptionRecord) -> Result<(), String> {
+    let target_dir = Path::new(&record.target_dir);
+    let all_files: Vec<&AdoptedFile> = record
+        .groups
+        .iter()
+        .flat_map(|group| &group.files)
+        .collect();
+
+    // Undoing without the originals would only delete the library copies
+    if record.mode == AdoptionMode::Copy {
+        let missing: Vec<String> = all_files
+            .iter()
+            .filter(|file| {
+                backup_path_for(record.backup_dir.as_deref(), Path::new(&file.original))
+                    .is_none_or(|backup| !backup.exists())
+            })
+            .map(|file| file.original.clone())
+            .collect();
+
+        if !missing.is_empty() {
+            return Err(format!(
+                "The originals of {} are missing from {}, not undoing the adoption",
+                missing.join(", "),
+                record
+                    .backup_dir
+                    .as_deref()
+                    .unwrap_or("the adoption folder")
+            ));
+        }
+    }
+
+    let errors = restore_files(record, &all_files);
+
+    // Restored files are the user's again
+    let mut manifest = managed_manifest::load_or_infer_manifest(target_dir)?;
+    for file in &all_files {
+        let original = Path::new(&file.original);
+        if original.is_file() && !original.is_symlink() {
+            if let Some(name) = original.file_name() {
+                manifest.remove(&name.to_string_lossy());
+            }
+        }
+    }
+    managed_manifest::save_manifest(target_dir, &manifest)?;
+
+    if !errors.is_empty() {
+        return Err(format!(
+            "Failed to restore some adopted files: {}",
+            errors.join("; ")
+        ));
+    }
+
+    Ok(())
+}
+
+fn get_adoptions_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(settings::app_data_dir(app)?.join(ADOPTIONS_DIR_NAME))
+}
+
+// Only accept plain adoption ids so an id can never point outside the adoptions folder
+fn adoption_record_path(app: &AppHandle, adoption_id: &str) -> Result<PathBuf, String> {
+    let is_valid = adoption_id
+        .strip_prefix("adoption-")
+        .is_some_and(|timestamp| timestamp.parse::<u64>().is_ok());
+    if !is_valid {
+        return Err(format!("Invalid adoption id: {}", adoption_id));
+    }
+
+    Ok(get_adoptions_dir(app)?.join(format!("{}.json", adoption_id)))
+}
+
+fn save_record(app: &AppHandle, record: &AdoptionRecord) -> Result<(), String> {
+    let path = adoption_record_path(app, &record.id)?;
+    if path.exists() {
+        return Err(format!("Adoption {} already exists", record.id));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create adoptions directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(record)
+        .map_err(|e| format!("Failed to serialize adoption record: {}", e))?;
+
+    fs::write(&path, content).map_err(|e| format!("Failed to write adoption record: {}", e))
+}
+
+fn load_record(path: &Path) -> Result<AdoptionRecord, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read adoption record: {}", e))?;
+
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse adoption record: {}", e))
+}
+
+#[command]
+pub fn scan_gsx_folder_for_adoption(app: AppHandle) -> Result<AdoptionScan, String> {
+    let target_dir = get_gsx_target_dir(&app)?;
+    scan_for_adoption(&target_dir, &get_library_root(&app)?)
+}
+
+/// The frontend has to list the returned groups as selected profiles, or the next activation
+/// removes the links of the adopted files
+#[command]
+pub fn adopt_gsx_profiles(
+    app: AppHandle,
+    mode: Option<AdoptionMode>,
+) -> Result<AdoptionRecord, String> {
+    let target_dir = get_gsx_target_dir(&app)?;
+    let settings = settings::load_settings(&app)?;
+
+    // Don't move files around underneath an activation that never finished
+    activation_transaction::recover_activation(&target_dir)?;
+
+    let scan = scan_for_adoption(&target_dir, &get_library_root(&app)?)?;
+    if scan.groups.is_empty() {
+        return Err("No GSX profiles found to adopt".to_string());
+    }
+
+    let record = adopt_profiles(
+        &scan,
+        &target_dir,
+        mode.unwrap_or_default(),
+        settings.link_strategy,
+    )?;
+
+    // Without the record the adoption can't be undone, so put everything back
+    if let Err(e) = save_record(&app, &record) {
+        undo_adoption(&record)?;
+        return Err(e);
+    }
+
+    println!(
+        "Adopted {} profiles from {:?} ({:?})",
+        record.groups.len(),
+        target_dir,
+        record.mode
+    );
+
+    Ok(record)
+}
+
+#[command]
+pub fn list_profile_adoptions(app: AppHandle) -> Result<Vec<AdoptionRecord>, String> {
+    let adoptions_dir = get_adoptions_dir(&app)?;
+    if !adoptions_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut records = Vec::new();
+    let entries = fs::read_dir(&adoptions_dir)
+        .map_err(|e| format!("Failed to read adoptions directory: {}", e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+
+        if path.extension().is_some_and(|ext| ext == "json") {
+            records.push(load_record(&path)?);
+        }
+    }
+
+    // Newest first
+    records.sort_by_key(|record| std::cmp::Reverse(record.timestamp));
+
+    Ok(records)
+}
+
+#[command]
+pub fn undo_profile_adoption(app: AppHandle, adoption_id: String) -> Result<(), String> {
+    let path = adoption_record_path(&app, &adoption_id)?;
+    if !path.exists() {
+        return Err(format!("Adoption not found: {}", adoption_id));
+    }
+
+    let record = load_record(&path)?;
+    activation_transaction::recover_activation(Path::new(&record.target_dir))?;
+
+    undo_adoption(&record)?;
+
+    fs::remove_file(&path).map_err(|e| format!("Failed to remove adoption record: {}", e))?;
+    println!("Undid adoption {}", adoption_id);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_profile_symlink::helpers;
+
+    struct Fixture {
+        _dir: tempfile::TempDir,
+        target: PathBuf,
+        scan: AdoptionScan,
+    }
+
+    fn fixture() -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("GSX");
+        let library_dir = dir
+            .path()
+            .join(PROFILE_LIBRARY_FOLDER)
+            .join(UNKNOWN_CONTINENT)
+            .join(UNKNOWN_COUNTRY)
+            .join("EDDM");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("eddm-aerosoft.ini"), "profile").unwrap();
+        fs::write(target.join("eddm-aerosoft.py"), "script").unwrap();
+
+        let scan = AdoptionScan {
+            target_dir: target.to_string_lossy().to_string(),
+            groups: vec![AdoptionGroup {
+                icao: "EDDM".to_string(),
+                developer: Some("aerosoft".to_string()),
+                library_dir: library_dir.to_string_lossy().to_string(),
+                files: ["eddm-aerosoft.ini", "eddm-aerosoft.py"]
+                    .iter()
+                    .map(|name| target.join(name).to_string_lossy().to_string())
+                    .collect(),
+            }],
+            skipped: Vec::new(),
+        };
+
+        Fixture {
+            _dir: dir,
+            target,
+            scan,
+        }
+    }
+
+    #[test]
+    fn copy_mode_keeps_originals_in_the_manager_folder() {
+        let fixture = fixture();
+
+        let record = adopt_profiles(
+            &fixture.scan,
+            &fixture.target,
+            AdoptionMode::Copy,
+            LinkStrategy::Copy,
+        )
+        .unwrap();
+
+        let backup_dir = PathBuf::from(record.backup_dir.clone().unwrap());
+        assert_eq!(backup_dir, originals_dir(&fixture.target, &record.id));
+        assert!(backup_dir.join("eddm-aerosoft.ini").is_file());
+        // Nothing the backup list or its pruning would pick up
+        let backup_folders = fs::read_dir(&fixture.target)
+            .unwrap()
+            .filter(|entry| helpers::is_backup_dir(&entry.as_ref().unwrap().path()))
+            .count();
+        assert_eq!(backup_folders, 0);
+
+        undo_adoption(&record).unwrap();
+
+        let original = fixture.target.join("eddm-aerosoft.ini");
+        assert!(!original.is_symlink());
+        assert_eq!(fs::read_to_string(&original).unwrap(), "profile");
+        assert!(!fixture
+            .target
+            .join(MANIFEST_DIR_NAME)
+            .join(ADOPTIONS_DIR_NAME)
+            .exists());
+        assert!(!Path::new(&record.groups[0].files[0].library).exists());
+    }
+
+    #[test]
+    fn undo_fails_when_the_originals_are_missing() {
+        let fixture = fixture();
+        let record = adopt_profiles(
+            &fixture.scan,
+            &fixture.target,
+            AdoptionMode::Copy,
+            LinkStrategy::Copy,
+        )
+        .unwrap();
+
+        fs::remove_dir_all(record.backup_dir.as_deref().unwrap()).unwrap();
+
+        let error = undo_adoption(&record).unwrap_err();
+        assert!(error.contains("missing"), "{}", error);
+        // The library copies are all that is left, so they stay
+        for file in &record.groups[0].files {
+            assert!(Path::new(&file.library).is_file());
+            assert!(Path::new(&file.original).exists());
+        }
+    }
+}
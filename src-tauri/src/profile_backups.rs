@@ -0,0 +1,647 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle};
+
+use crate::activation_history;
+use crate::activation_progress::ProgressReporter;
+use crate::activation_report::{ActivationError, ActivationReport};
+use crate::activation_transaction;
+use crate::backup_archive::{self, BackupArchiveManifest};
+use crate::backup_store;
+use crate::create_profile_symlink::helpers;
+use crate::gsx_target::get_gsx_target_dir;
+use crate::settings::{self, BackendSettings};
+use crate::sim_guard::SimGuard;
+
+// Default backup location for archives, inside the app data folder
+const BACKUP_ARCHIVE_FOLDER: &str = "backups";
+
+/// How activations back up the GSX folder
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupFormat {
+    #[default]
+    Folder, // backup-<timestamp> folder inside the GSX folder
+    Zip, // backup-<timestamp>.zip with a manifest, in the backup location
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BackupInfo {
+    pub id: String, // Folder or archive name, e.g. "backup-1712345678" or "backup-1712345678.zip"
+    pub path: String,
+    pub format: BackupFormat,
+    pub timestamp: u64, // Unix seconds taken from the name
+    pub file_count: usize,
+    pub size_bytes: u64, // Archives report their compressed size on disk
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BackupFile {
+    pub name: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BackupDetails {
+    pub info: BackupInfo,
+    pub files: Vec<BackupFile>,
+    pub manifest: Option<BackupArchiveManifest>, // Only archives have one
+}
+
+/// Which backups survive a prune. Both limits are optional; a backup is deleted if it
+/// falls outside either of them. The newest backup is always kept.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    pub keep_last: Option<usize>,
+    pub max_age_days: Option<u64>,
+}
+
+// Parse the unix timestamp out of a "backup-<timestamp>" folder name
+pub fn parse_backup_timestamp(name: &str) -> Option<u64> {
+    name.strip_prefix("backup-")?.parse().ok()
+}
+
+// Parse the timestamp and format out of a "backup-<timestamp>[.zip]" name
+fn parse_backup_id(name: &str) -> Option<(u64, BackupFormat)> {
+    match name.strip_suffix(".zip") {
+        Some(stem) => Some((parse_backup_timestamp(stem)?, BackupFormat::Zip)),
+        None => Some((parse_backup_timestamp(name)?, BackupFormat::Folder)),
+    }
+}
+
+/// The backup location from the settings, or the backups folder in the app data folder
+pub fn get_archive_dir(app: &AppHandle, settings: &BackendSettings) -> Result<PathBuf, String> {
+    if let Some(location) = &settings.backup_location {
+        return Ok(PathBuf::from(location));
+    }
+
+    Ok(settings::app_data_dir(app)?.join(BACKUP_ARCHIVE_FOLDER))
+}
+
+// Only accept plain backup names so an id can never point outside the backup folders.
+// Folders live in the GSX folder, archives in the backup location.
+fn backup_path(
+    target_dir: &Path,
+    archive_dir: Option<&Path>,
+    backup_id: &str,
+) -> Result<(PathBuf, BackupFormat), String> {
+    let (_, format) =
+        parse_backup_id(backup_id).ok_or_else(|| format!("Invalid backup id: {}", backup_id))?;
+
+    let path = match format {
+        BackupFormat::Folder => Some(target_dir.join(backup_id)).filter(|path| path.is_dir()),
+        BackupFormat::Zip => archive_dir
+            .map(|dir| dir.join(backup_id))
+            .filter(|path| path.is_file() && archive_belongs_to(path, target_dir)),
+    };
+
+    path.map(|path| (path, format))
+        .ok_or_else(|| format!("Backup not found: {}", backup_id))
+}
+
+// A shared backup location holds the archives of several GSX folders
+fn archive_belongs_to(archive_path: &Path, target_dir: &Path) -> bool {
+    backup_archive::read_archive_manifest(archive_path)
+        .is_ok_and(|manifest| Path::new(&manifest.target_dir) == target_dir)
+}
+
+fn read_backup_files(backup_dir: &Path) -> Result<Vec<BackupFile>, String> {
+    if let Some(manifest) = backup_store::load_backup_manifest(backup_dir)? {
+        return Ok(manifest
+            .files
+            .into_iter()
+            .map(|file| BackupFile {
+                name: file.name,
+                size_bytes: file.size_bytes,
+            })
+            .collect());
+    }
+
+    let mut files = Vec::new();
+
+    let entries =
+        fs::read_dir(backup_dir).map_err(|e| format!("Failed to read backup directory: {}", e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+
+        if path.is_file() {
+            let metadata = fs::metadata(&path)
+                .map_err(|e| format!("Failed to read metadata for {:?}: {}", path, e))?;
+
+            files.push(BackupFile {
+                name: entry.file_name().to_string_lossy().to_string(),
+                size_bytes: metadata.len(),
+            });
+        }
+    }
+
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+// The files listed in an archive's manifest
+fn read_archive_files(manifest: &BackupArchiveManifest) -> Vec<BackupFile> {
+    manifest
+        .files
+        .iter()
+        .map(|file| BackupFile {
+            name: file.name.clone(),
+            size_bytes: file.size_bytes,
+        })
+        .collect()
+}
+
+fn read_backup_details(path: &Path, format: BackupFormat) -> Result<BackupDetails, String> {
+    let id = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| format!("Failed to get backup name from {:?}", path))?;
+    let (timestamp, _) =
+        parse_backup_id(&id).ok_or_else(|| format!("Invalid backup name: {}", id))?;
+
+    let (files, manifest, size_bytes) = match format {
+        BackupFormat::Folder => {
+            let files = read_backup_files(path)?;
+            let size_bytes = files.iter().map(|file| file.size_bytes).sum();
+            (files, None, size_bytes)
+        }
+        BackupFormat::Zip => {
+            let manifest = backup_archive::read_archive_manifest(path)?;
+            let size_bytes = fs::metadata(path)
+                .map_err(|e| format!("Failed to read metadata for {:?}: {}", path, e))?
+                .len();
+            (read_archive_files(&manifest), Some(manifest), size_bytes)
+        }
+    };
+
+    Ok(BackupDetails {
+        info: BackupInfo {
+            id,
+            path: path.to_string_lossy().to_string(),
+            format,
+            timestamp,
+            file_count: files.len(),
+            size_bytes,
+        },
+        files,
+        manifest,
+    })
+}
+
+/// List all backup folders in the GSX folder and the archives of that folder in the backup
+/// location, newest first. Backups that can't be read are skipped.
+pub fn list_backups(
+    target_dir: &Path,
+    archive_dir: Option<&Path>,
+) -> Result<Vec<BackupInfo>, String> {
+    let mut backups = Vec::new();
+
+    if target_dir.exists() {
+        let entries = fs::read_dir(target_dir)
+            .map_err(|e| format!("Failed to read target directory: {}", e))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let path = entry.path();
+
+            // Ignore folders that only look like backups, e.g. "backup-old"
+            if helpers::is_backup_dir(&path)
+                && parse_backup_timestamp(&entry.file_name().to_string_lossy()).is_some()
+            {
+                match read_backup_details(&path, BackupFormat::Folder) {
+                    Ok(details) => backups.push(details.info),
+                    Err(e) => println!("Skipping unreadable backup {:?}: {}", path, e),
+                }
+            }
+        }
+    }
+
+    if let Some(archive_dir) = archive_dir.filter(|dir| dir.exists()) {
+        let entries = fs::read_dir(archive_dir)
+            .map_err(|e| format!("Failed to read backup directory: {}", e))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            // Unfinished ".zip.partial" archives don't parse and are skipped
+            if path.is_file()
+                && parse_backup_id(&name).map(|(_, format)| format) == Some(BackupFormat::Zip)
+            {
+                match read_backup_details(&path, BackupFormat::Zip) {
+                    Ok(details) => {
+                        let belongs_to_target = details
+                            .manifest
+                            .as_ref()
+                            .is_some_and(|manifest| Path::new(&manifest.target_dir) == target_dir);
+                        if belongs_to_target {
+                            backups.push(details.info);
+                        }
+                    }
+                    Err(e) => println!("Skipping unreadable backup {:?}: {}", path, e),
+                }
+            }
+        }
+    }
+
+    backups.sort_by_key(|backup| Reverse(backup.timestamp));
+    Ok(backups)
+}
+
+/// Replace the current profiles in the GSX folder with the files from a backup. Like an
+/// activation this runs behind the sim guard and either restores every file or none.
+/// Returns the number of restored files and the report for the activation history.
+pub fn restore_backup(
+    target_dir: &Path,
+    archive_dir: Option<&Path>,
+    backup_id: &str,
+    guard: &SimGuard,
+    progress: &ProgressReporter,
+) -> Result<(usize, ActivationReport), ActivationError> {
+    let started_at = Instant::now();
+    let (backup_path, format) =
+        backup_path(target_dir, archive_dir, backup_id).map_err(ActivationError::backup)?;
+
+    // Read and verified before anything in the GSX folder is touched
+    let files = match format {
+        BackupFormat::Folder => backup_store::read_backup(target_dir, &backup_path),
+        BackupFormat::Zip => backup_archive::read_archived_files(&backup_path).map(|files| {
+            files
+                .into_iter()
+                .map(|(file, content)| (file.name, content))
+                .collect()
+        }),
+    }
+    .map_err(ActivationError::backup)?;
+
+    let sim_running = guard.check(None, progress)?;
+    let removed = activation_transaction::restore_transactionally(&files, target_dir)
+        .map_err(ActivationError::link)?;
+
+    let report = ActivationReport {
+        target_dir: target_dir.to_string_lossy().to_string(),
+        removed,
+        sim_running,
+        duration_ms: started_at.elapsed().as_millis() as u64,
+        ..Default::default()
+    };
+
+    Ok((files.len(), report))
+}
+
+pub fn delete_backup(
+    target_dir: &Path,
+    archive_dir: Option<&Path>,
+    backup_id: &str,
+) -> Result<(), String> {
+    let (backup_path, format) = backup_path(target_dir, archive_dir, backup_id)?;
+
+    println!("Deleting backup: {:?}", backup_path);
+    match format {
+        BackupFormat::Folder => {
+            fs::remove_dir_all(&backup_path)
+                .map_err(|e| format!("Failed to delete backup: {}", e))?;
+
+            // Drop the stored files only this backup referred to
+            let removed = backup_store::collect_garbage(target_dir)?;
+            if removed > 0 {
+                println!("Removed {} unused backup objects", removed);
+            }
+            Ok(())
+        }
+        BackupFormat::Zip => {
+            fs::remove_file(&backup_path).map_err(|e| format!("Failed to delete backup: {}", e))
+        }
+    }
+}
+
+/// Delete every backup that falls outside the retention policy and return the deleted ids.
+/// Folders and archives count towards the same limits.
+pub fn prune_backups(
+    target_dir: &Path,
+    archive_dir: Option<&Path>,
+    policy: &RetentionPolicy,
+) -> Result<Vec<String>, String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| format!("Failed to get system time: {}", e))?
+        .as_secs();
+
+    let mut deleted = Vec::new();
+
+    // Backups are sorted newest first, so the index is the number of newer backups
+    for (index, backup) in list_backups(target_dir, archive_dir)?.iter().enumerate() {
+        if index == 0 {
+            continue;
+        }
+
+        let too_many = policy.keep_last.is_some_and(|keep_last| index >= keep_last);
+        let too_old = policy
+            .max_age_days
+            .is_some_and(|days| now.saturating_sub(backup.timestamp) > days * 24 * 60 * 60);
+
+        if too_many || too_old {
+            delete_backup(target_dir, archive_dir, &backup.id)?;
+            deleted.push(backup.id.clone());
+        }
+    }
+
+    Ok(deleted)
+}
+
+// The GSX folder and the backup location, for the commands
+fn backup_dirs(app: &AppHandle) -> Result<(PathBuf, PathBuf), String> {
+    let settings = settings::load_settings(app)?;
+    Ok((get_gsx_target_dir(app)?, get_archive_dir(app, &settings)?))
+}
+
+#[command]
+pub fn list_profile_backups(app: AppHandle) -> Result<Vec<BackupInfo>, String> {
+    let (target_dir, archive_dir) = backup_dirs(&app)?;
+    list_backups(&target_dir, Some(&archive_dir))
+}
+
+#[command]
+pub fn get_profile_backup(app: AppHandle, backup_id: String) -> Result<BackupDetails, String> {
+    let (target_dir, archive_dir) = backup_dirs(&app)?;
+    let (backup_path, format) = backup_path(&target_dir, Some(&archive_dir), &backup_id)?;
+
+    read_backup_details(&backup_path, format)
+}
+
+#[command]
+pub async fn restore_profile_backup(
+    app: AppHandle,
+    backup_id: String,
+) -> Result<String, ActivationError> {
+    let target_dir = get_gsx_target_dir(&app).map_err(ActivationError::target_dir)?;
+    let settings = settings::load_settings(&app).map_err(ActivationError::settings)?;
+    let archive_dir = get_archive_dir(&app, &settings).map_err(ActivationError::settings)?;
+    let progress = ProgressReporter::for_app(&app);
+    let guard = SimGuard::system(settings.sim_guard.clone());
+
+    // Queueing for the simulator waits, so keep it off the async worker threads
+    tauri::async_runtime::spawn_blocking(move || {
+        let previous_files = activation_history::active_files(&target_dir).unwrap_or_default();
+
+        let result = restore_backup(
+            &target_dir,
+            Some(&archive_dir),
+            &backup_id,
+            &guard,
+            &progress,
+        );
+        progress.finish();
+        let (restored_count, report) = result?;
+
+        activation_history::record_activation(&app, previous_files, &report);
+
+        Ok(format!(
+            "Restored {} profile files from {}.",
+            restored_count, backup_id
+        ))
+    })
+    .await
+    .map_err(|e| ActivationError::Link {
+        message: format!("Restore task failed: {}", e),
+    })?
+}
+
+#[command]
+pub fn delete_profile_backup(app: AppHandle, backup_id: String) -> Result<(), String> {
+    let (target_dir, archive_dir) = backup_dirs(&app)?;
+    delete_backup(&target_dir, Some(&archive_dir), &backup_id)
+}
+
+// Prune with an explicit policy, or with the one saved in the backend settings
+#[command]
+pub fn prune_profile_backups(
+    app: AppHandle,
+    policy: Option<RetentionPolicy>,
+) -> Result<Vec<String>, String> {
+    let policy = match policy {
+        Some(policy) => policy,
+        None => settings::load_settings(&app)?.backup_retention,
+    };
+
+    let (target_dir, archive_dir) = backup_dirs(&app)?;
+    prune_backups(&target_dir, Some(&archive_dir), &policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link_strategy::{self, LinkStrategy};
+    use crate::managed_manifest::{self, UnmanagedPolicy};
+    use crate::sim_guard::{ProcessLister, SimGuardSettings, SimRunningPolicy};
+
+    fn setup() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("GSX");
+        let archive_dir = dir.path().join("backups");
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(&archive_dir).unwrap();
+        fs::write(target.join("eddm-aerosoft.ini"), "profile").unwrap();
+
+        (dir, target, archive_dir)
+    }
+
+    // Archive the folder under a fixed name so tests control the timestamps
+    fn archive(target: &Path, archive_dir: &Path, id: &str) {
+        let progress = ProgressReporter::default();
+        let path =
+            backup_archive::create_backup_archive(target, archive_dir, &[], &progress).unwrap();
+        fs::rename(path, archive_dir.join(id)).unwrap();
+    }
+
+    struct Processes(Vec<String>);
+
+    impl ProcessLister for Processes {
+        fn running_processes(&self) -> Result<Vec<String>, String> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn guard(running: &[&str]) -> SimGuard {
+        SimGuard::new(
+            Box::new(Processes(
+                running.iter().map(|name| name.to_string()).collect(),
+            )),
+            SimGuardSettings {
+                policy: SimRunningPolicy::Refuse,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn restore(
+        target: &Path,
+        archive_dir: &Path,
+        running: &[&str],
+    ) -> Result<usize, ActivationError> {
+        restore_backup(
+            target,
+            Some(archive_dir),
+            "backup-100.zip",
+            &guard(running),
+            &ProgressReporter::default(),
+        )
+        .map(|(count, _)| count)
+    }
+
+    fn ids(backups: &[BackupInfo]) -> Vec<&str> {
+        backups.iter().map(|backup| backup.id.as_str()).collect()
+    }
+
+    #[test]
+    fn skips_backups_that_cant_be_read() {
+        let (_dir, target, archive_dir) = setup();
+        fs::create_dir_all(target.join("backup-100")).unwrap();
+        fs::write(target.join("backup-100").join("egll.ini"), "old").unwrap();
+        fs::create_dir_all(target.join("backup-200")).unwrap();
+        fs::write(
+            target
+                .join("backup-200")
+                .join(backup_store::BACKUP_MANIFEST_NAME),
+            "not json",
+        )
+        .unwrap();
+        fs::write(archive_dir.join("backup-300.zip"), "not a zip").unwrap();
+
+        let backups = list_backups(&target, Some(&archive_dir)).unwrap();
+        assert_eq!(ids(&backups), vec!["backup-100"]);
+
+        let policy = RetentionPolicy {
+            keep_last: Some(1),
+            max_age_days: None,
+        };
+        assert!(prune_backups(&target, Some(&archive_dir), &policy)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn leaves_the_archives_of_other_folders_alone() {
+        let (dir, target, archive_dir) = setup();
+        let other = dir.path().join("GSX 2024");
+        fs::create_dir_all(&other).unwrap();
+        fs::write(other.join("egll-justsim.ini"), "other").unwrap();
+
+        archive(&other, &archive_dir, "backup-100.zip");
+        archive(&target, &archive_dir, "backup-200.zip");
+        fs::write(target.join("eddm-aerosoft.ini"), "edited").unwrap();
+        archive(&target, &archive_dir, "backup-300.zip");
+
+        let backups = list_backups(&target, Some(&archive_dir)).unwrap();
+        assert_eq!(ids(&backups), vec!["backup-300.zip", "backup-200.zip"]);
+
+        let policy = RetentionPolicy {
+            keep_last: Some(1),
+            max_age_days: None,
+        };
+        let deleted = prune_backups(&target, Some(&archive_dir), &policy).unwrap();
+        assert_eq!(deleted, vec!["backup-200.zip"]);
+        assert!(archive_dir.join("backup-100.zip").is_file());
+
+        assert!(delete_backup(&target, Some(&archive_dir), "backup-100.zip").is_err());
+        assert!(archive_dir.join("backup-100.zip").is_file());
+    }
+
+    #[test]
+    fn restore_replaces_links_and_keeps_other_user_files() {
+        let (dir, target, archive_dir) = setup();
+        fs::create_dir_all(target.join("sub")).unwrap();
+        fs::write(target.join("sub").join("helper.py"), "helper").unwrap();
+        archive(&target, &archive_dir, "backup-100.zip");
+
+        // Since then the profile was activated from the library and the user added files
+        let library = dir.path().join("gsx-profiles");
+        fs::create_dir_all(&library).unwrap();
+        fs::write(library.join("eddm-aerosoft.ini"), "library").unwrap();
+        fs::remove_file(target.join("eddm-aerosoft.ini")).unwrap();
+        activation_transaction::activate_transactionally(
+            vec![library
+                .join("eddm-aerosoft.ini")
+                .to_string_lossy()
+                .to_string()],
+            &target,
+            LinkStrategy::Auto,
+            UnmanagedPolicy::Preserve,
+            &ProgressReporter::default(),
+        )
+        .unwrap();
+        fs::write(target.join("sub").join("helper.py"), "edited").unwrap();
+        fs::write(target.join("sub").join("keep.py"), "keep").unwrap();
+        fs::write(target.join("notes.txt"), "notes").unwrap();
+
+        assert_eq!(restore(&target, &archive_dir, &[]).unwrap(), 2);
+
+        let profile = target.join("eddm-aerosoft.ini");
+        assert!(!profile.is_symlink());
+        assert_eq!(fs::read_to_string(&profile).unwrap(), "profile");
+        assert_eq!(
+            fs::read_to_string(library.join("eddm-aerosoft.ini")).unwrap(),
+            "library"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("sub").join("helper.py")).unwrap(),
+            "helper"
+        );
+        assert!(target.join("sub").join("keep.py").is_file());
+        assert!(target.join("notes.txt").is_file());
+        assert!(managed_manifest::load_or_infer_manifest(&target)
+            .unwrap()
+            .entries
+            .is_empty());
+        assert!(!target.join(".gsx-activation-staging").exists());
+        assert!(!target.join(".gsx-activation-rollback").exists());
+    }
+
+    #[test]
+    fn restore_replaces_a_leftover_link_instead_of_writing_through_it() {
+        let (dir, target, archive_dir) = setup();
+        archive(&target, &archive_dir, "backup-100.zip");
+
+        // A link the manager does not know about, into the user's library
+        let library_file = dir.path().join("eddm-aerosoft.ini");
+        fs::write(&library_file, "library").unwrap();
+        fs::remove_file(target.join("eddm-aerosoft.ini")).unwrap();
+        link_strategy::create_link(
+            &library_file,
+            &target.join("eddm-aerosoft.ini"),
+            LinkStrategy::Auto,
+        )
+        .unwrap();
+
+        restore(&target, &archive_dir, &[]).unwrap();
+
+        assert!(!target.join("eddm-aerosoft.ini").is_symlink());
+        assert_eq!(
+            fs::read_to_string(target.join("eddm-aerosoft.ini")).unwrap(),
+            "profile"
+        );
+        assert_eq!(fs::read_to_string(&library_file).unwrap(), "library");
+    }
+
+    #[test]
+    fn restore_waits_for_the_sim_guard() {
+        let (_dir, target, archive_dir) = setup();
+        archive(&target, &archive_dir, "backup-100.zip");
+        fs::write(target.join("eddm-aerosoft.ini"), "edited").unwrap();
+
+        let error = restore(&target, &archive_dir, &["FlightSimulator.exe"]).unwrap_err();
+
+        assert!(matches!(error, ActivationError::SimulatorRunning { .. }));
+        assert_eq!(
+            fs::read_to_string(target.join("eddm-aerosoft.ini")).unwrap(),
+            "edited"
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::command;
+
+use crate::profile_metadata::ProfileFileInfo;
+
+/// What to do when two selected profiles would end up as the same file, or when more
+/// than one profile is selected for the same airport
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    // Refuse to activate and report the conflicts
+    #[default]
+    Fail,
+    // Keep the profile with the most recently modified files
+    PreferNewest,
+    // Keep the profile from this developer, falling back to the first one
+    PreferDeveloper {
+        developer: String,
+    },
+    // Keep whichever profile was selected first
+    KeepFirst,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    FileName, // Two sources share a file name, so only one can be linked
+    Icao,     // Profiles from several folders are selected for the same airport
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ProfileConflict {
+    pub kind: ConflictKind,
+    pub key: String, // Lower case file name or upper case ICAO
+    pub files: Vec<String>,
+    pub kept: Option<String>, // Winning file or profile folder, None when only detecting
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ConflictReport {
+    pub conflicts: Vec<ProfileConflict>,
+    pub kept_files: Vec<String>,
+    pub dropped_files: Vec<String>,
+}
+
+struct Candidate {
+    info: ProfileFileInfo,
+    folder: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl Candidate {
+    fn new(file_path: &str) -> Candidate {
+        let path = PathBuf::from(file_path);
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+
+        Candidate {
+            info: ProfileFileInfo::from_path(&path),
+            folder: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            modified,
+        }
+    }
+
+    fn is_ini(&self) -> bool {
+        self.info.file_name.to_lowercase().ends_with(".ini")
+    }
+}
+
+// Pick the winner among candidate groups (given as indices into `candidates`, in
+// selection order). Each group is a profile folder or a single file.
+fn pick_winner(candidates: &[Candidate], groups: &[Vec<usize>], policy: &ConflictPolicy) -> usize {
+    match policy {
+        ConflictPolicy::Fail | ConflictPolicy::KeepFirst => 0,
+        ConflictPolicy::PreferNewest => {
+            let newest = |group: &Vec<usize>| {
+                group
+                    .iter()
+                    .filter_map(|&index| candidates[index].modified)
+                    .max()
+            };
+
+            // max_by_key returns the last maximum, so walk backwards to prefer earlier groups on ties
+            (0..groups.len())
+                .rev()
+                .max_by_key(|&group| newest(&groups[group]))
+                .unwrap_or(0)
+        }
+        ConflictPolicy::PreferDeveloper { developer } => groups
+            .iter()
+            .position(|group| {
+                group.iter().any(|&index| {
+                    candidates[index]
+                        .info
+                        .developer
+                        .as_deref()
+                        .is_some_and(|d| d.eq_ignore_ascii_case(developer.trim()))
+                })
+            })
+            .unwrap_or(0),
+    }
+}
+
+// Airports with .ini profiles from more than one folder, as (icao, folders in selection order)
+fn find_icao_conflicts(candidates: &[Candidate], active: &[bool]) -> Vec<(String, Vec<PathBuf>)> {
+    let mut folders_by_icao: Vec<(String, Vec<PathBuf>)> = Vec::new();
+
+    for (index, candidate) in candidates.iter().enumerate() {
+        if !active[index] || !candidate.is_ini() {
+            continue;
+        }
+        let icao = match &candidate.info.icao {
+            Some(icao) => icao,
+            None => continue,
+        };
+
+        match folders_by_icao.iter_mut().find(|(key, _)| key == icao) {
+            Some((_, folders)) => {
+                if !folders.contains(&candidate.folder) {
+                    folders.push(candidate.folder.clone());
+                }
+            }
+            None => folders_by_icao.push((icao.clone(), vec![candidate.folder.clone()])),
+        }
+    }
+
+    folders_by_icao.retain(|(_, folders)| folders.len() > 1);
+    folders_by_icao
+}
+
+// Sources that share a file name, as (lower case name, indices in selection order)
+fn find_file_name_conflicts(
+    candidates: &[Candidate],
+    active: &[bool],
+) -> Vec<(String, Vec<usize>)> {
+    let mut order = Vec::new();
+    let mut indices_by_name: HashMap<String, Vec<usize>> = HashMap::new();
+
+    for (index, candidate) in candidates.iter().enumerate() {
+        if !active[index] {
+            continue;
+        }
+        let key = candidate.info.file_name.to_lowercase();
+        let indices = indices_by_name.entry(key.clone()).or_default();
+
+        // The same source selected twice is not a conflict
+        if indices
+            .iter()
+            .any(|&other| candidates[other].info.path == candidate.info.path)
+        {
+            continue;
+        }
+        if indices.is_empty() {
+            order.push(key);
+        }
+        indices.push(index);
+    }
+
+    order
+        .into_iter()
+        .filter_map(|key| {
+            let indices = indices_by_name.remove(&key)?;
+            (indices.len() > 1).then_some((key, indices))
+        })
+        .collect()
+}
+
+/// Find every conflict in the selection, for display before activation
+pub fn detect_conflicts(file_paths: &[String]) -> Vec<ProfileConflict> {
+    let candidates: Vec<Candidate> = file_paths.iter().map(|path| Candidate::new(path)).collect();
+    let active = vec![true; candidates.len()];
+
+    let mut conflicts = Vec::new();
+
+    for (icao, folders) in find_icao_conflicts(&candidates, &active) {
+        conflicts.push(ProfileConflict {
+            kind: ConflictKind::Icao,
+            key: icao.clone(),
+            files: icao_files(&candidates, &active, &icao, &folders),
+            kept: None,
+        });
+    }
+
+    for (name, indices) in find_file_name_conflicts(&candidates, &active) {
+        conflicts.push(ProfileConflict {
+            kind: ConflictKind::FileName,
+            key: name,
+            files: indices
+                .iter()
+                .map(|&index| candidates[index].info.path.clone())
+                .collect(),
+            kept: None,
+        });
+    }
+
+    conflicts
+}
+
+fn icao_files(
+    candidates: &[Candidate],
+    active: &[bool],
+    icao: &str,
+    folders: &[PathBuf],
+) -> Vec<String> {
+    candidates
+        .iter()
+        .enumerate()
+        .filter(|(index, candidate)| {
+            active[*index]
+                && candidate.info.icao.as_deref() == Some(icao)
+                && folders.contains(&candidate.folder)
+        })
+        .map(|(_, candidate)| candidate.info.path.clone())
+        .collect()
+}
+
+/// Apply the conflict policy to the selection. Airport conflicts are resolved first by
+/// keeping one profile folder per ICAO, then any remaining file name clashes are resolved
+/// per file. With ConflictPolicy::Fail, any conflict is returned as an error.
+pub fn resolve_conflicts(
+    file_paths: &[String],
+    policy: &ConflictPolicy,
+) -> Result<ConflictReport, String> {
+    if *policy == ConflictPolicy::Fail {
+        let conflicts = detect_conflicts(file_paths);
+        if !conflicts.is_empty() {
+            return Err(describe_conflicts(&conflicts));
+        }
+
+        return Ok(ConflictReport {
+            conflicts,
+            kept_files: file_paths.to_vec(),
+            dropped_files: Vec::new(),
+        });
+    }
+
+    let candidates: Vec<Candidate> = file_paths.iter().map(|path| Candidate::new(path)).collect();
+    let mut active = vec![true; candidates.len()];
+    let mut conflicts = Vec::new();
+
+    for (icao, folders) in find_icao_conflicts(&candidates, &active) {
+        let files = icao_files(&candidates, &active, &icao, &folders);
+
+        let groups: Vec<Vec<usize>> = folders
+            .iter()
+            .map(|folder| {
+                (0..candidates.len())
+                    .filter(|&index| {
+                        candidates[index].folder == *folder
+                            && candidates[index].info.icao.as_deref() == Some(icao.as_str())
+                    })
+                    .collect()
+            })
+            .collect();
+        let winner = &folders[pick_winner(&candidates, &groups, policy)];
+
+        for (index, candidate) in candidates.iter().enumerate() {
+            if candidate.info.icao.as_deref() == Some(icao.as_str()) && candidate.folder != *winner
+            {
+                active[index] = false;
+            }
+        }
+
+        conflicts.push(ProfileConflict {
+            kind: ConflictKind::Icao,
+            key: icao,
+            files,
+            kept: Some(winner.to_string_lossy().to_string()),
+        });
+    }
+
+    for (name, indices) in find_file_name_conflicts(&candidates, &active) {
+        let groups: Vec<Vec<usize>> = indices.iter().map(|&index| vec![index]).collect();
+        let winner = indices[pick_winner(&candidates, &groups, policy)];
+
+        for &index in &indices {
+            if index != winner {
+                active[index] = false;
+            }
+        }
+
+        conflicts.push(ProfileConflict {
+            kind: ConflictKind::FileName,
+            key: name,
+            files: indices
+                .iter()
+                .map(|&index| candidates[index].info.path.clone())
+                .collect(),
+            kept: Some(candidates[winner].info.path.clone()),
+        });
+    }
+
+    let mut report = ConflictReport {
+        conflicts,
+        ..Default::default()
+    };
+
+    // Keep the original selection order, and only list each source once
+    let mut seen = HashSet::new();
+    for (index, file_path) in file_paths.iter().enumerate() {
+        if !active[index] {
+            report.dropped_files.push(file_path.clone());
+        } else if seen.insert(file_path.clone()) {
+            report.kept_files.push(file_path.clone());
+        }
+    }
+
+    Ok(report)
+}
+
+pub fn describe_conflicts(conflicts: &[ProfileConflict]) -> String {
+    let descriptions: Vec<String> = conflicts
+        .iter()
+        .map(|conflict| match conflict.kind {
+            ConflictKind::FileName => format!(
+                "{} is provided by {} profiles",
+                conflict.key,
+                conflict.files.len()
+            ),
+            ConflictKind::Icao => format!(
+                "{} is covered by {} files from different profiles",
+                conflict.key,
+                conflict.files.len()
+            ),
+        })
+        .collect();
+
+    format!(
+        "Selected profiles conflict with each other: {}",
+        descriptions.join("; ")
+    )
+}
+
+#[command]
+pub fn detect_profile_conflicts(selected_files: Vec<String>) -> Vec<ProfileConflict> {
+    detect_conflicts(&selected_files)
+}
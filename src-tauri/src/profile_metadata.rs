@@ -0,0 +1,97 @@
+use regex::Regex;
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::AppHandle;
+
+use crate::settings;
+
+// Name of the library folder the frontend saves profiles into (see saveFilesToNestedPath)
+pub const PROFILE_LIBRARY_FOLDER: &str = "gsx-profiles";
+
+// The profile library in the app data folder
+pub fn get_library_root(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(settings::app_data_dir(app)?.join(PROFILE_LIBRARY_FOLDER))
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ProfileFileInfo {
+    pub path: String,
+    pub file_name: String,
+    pub icao: Option<String>, // Upper case, e.g. "EDDM"
+    pub developer: Option<String>,
+}
+
+// GSX profiles are named "<icao>-<developer>.ini", e.g. "eddm-aerosoft.ini"
+fn file_name_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^([A-Za-z0-9]{4})(?:$|[-_ .]([^-_ .]+)?)").expect("valid profile name regex")
+    })
+}
+
+/// Guess ICAO and developer from a profile file name like "eddm-aerosoft.ini"
+pub fn parse_profile_file_name(file_name: &str) -> (Option<String>, Option<String>) {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    match file_name_regex().captures(&stem) {
+        Some(cap) => (
+            cap.get(1).map(|icao| icao.as_str().to_uppercase()),
+            cap.get(2).map(|developer| developer.as_str().to_string()),
+        ),
+        None => (None, None),
+    }
+}
+
+/// Read ICAO and developer from the library layout
+/// gsx-profiles/<continent>/<country>/<icao>/[<developer>/][<version>/]<file>
+pub fn parse_library_path(path: &Path) -> (Option<String>, Option<String>) {
+    let segments: Vec<String> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(segment) => Some(segment.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+
+    let library_index = match segments
+        .iter()
+        .rposition(|segment| segment.eq_ignore_ascii_case(PROFILE_LIBRARY_FOLDER))
+    {
+        Some(index) => index,
+        None => return (None, None),
+    };
+
+    // Everything between the library folder and the file name
+    let folders = &segments[library_index + 1..segments.len().saturating_sub(1)];
+
+    let icao = folders.get(2).map(|icao| icao.to_uppercase());
+    let developer = folders.get(3).cloned();
+
+    (icao, developer)
+}
+
+impl ProfileFileInfo {
+    /// Collect what can be known about a profile file from its path alone.
+    /// The library layout wins because it holds what the user entered when importing;
+    /// the file name is the fallback for files outside the library.
+    pub fn from_path(path: &Path) -> ProfileFileInfo {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let (library_icao, library_developer) = parse_library_path(path);
+        let (name_icao, name_developer) = parse_profile_file_name(&file_name);
+
+        ProfileFileInfo {
+            path: path.to_string_lossy().to_string(),
+            file_name,
+            icao: library_icao.or(name_icao),
+            developer: library_developer.or(name_developer),
+        }
+    }
+}
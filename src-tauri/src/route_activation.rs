@@ -0,0 +1,423 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+use tauri::{command, AppHandle};
+
+use crate::activation_history;
+use crate::activation_report::{ActivationError, ActivationReport};
+use crate::create_profile_symlink::{self, ActivationOptions};
+use crate::gsx_target::get_gsx_target_dir;
+use crate::profile_adoption::is_profile_file;
+use crate::profile_metadata::{get_library_root, ProfileFileInfo};
+
+/// Airports of a flight, given directly or read from a flight plan file
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RouteRequest {
+    pub departure: Option<String>,
+    pub arrival: Option<String>,
+    pub alternates: Vec<String>,
+    pub flight_plan_path: Option<String>, // MSFS .pln or SimBrief XML
+    // Keep the links of airports that are not on the route instead of removing them
+    pub keep_other_links: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RouteAirport {
+    pub icao: String,
+    pub folder: Option<String>, // The profile folder picked for this airport
+    pub files: Vec<String>,     // Its profile files, empty if there is no profile
+    pub other_folders: Vec<String>, // Further profile folders for this airport, not activated
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct RouteMatch {
+    pub airports: Vec<RouteAirport>, // In route order: departure, arrival, alternates
+    pub unmatched: Vec<String>,      // Airports without a profile in the library
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RouteActivationReport {
+    pub route: RouteMatch,
+    pub activation: ActivationReport,
+}
+
+/// Airports read from a flight plan, in the order departure, arrival, alternates
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct FlightPlanAirports {
+    pub departure: Option<String>,
+    pub arrival: Option<String>,
+    pub alternates: Vec<String>,
+}
+
+struct FlightPlanPatterns {
+    msfs_departure: Regex,
+    msfs_destination: Regex,
+    simbrief_origin: Regex,
+    simbrief_destination: Regex,
+    simbrief_alternate: Regex,
+}
+
+fn flight_plan_patterns() -> &'static FlightPlanPatterns {
+    static PATTERNS: OnceLock<FlightPlanPatterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        // SimBrief: <origin><icao_code>EDDM</icao_code>...</origin>
+        let section_icao = |section: &str| {
+            Regex::new(&format!(
+                r"(?is)<{0}>.*?<icao_code>\s*([A-Za-z0-9]{{4}})\s*</icao_code>.*?</{0}>",
+                section
+            ))
+            .expect("valid flight plan regex")
+        };
+
+        FlightPlanPatterns {
+            msfs_departure: Regex::new(r"(?i)<DepartureID>\s*([A-Za-z0-9]{4})\s*</DepartureID>")
+                .expect("valid flight plan regex"),
+            msfs_destination: Regex::new(
+                r"(?i)<DestinationID>\s*([A-Za-z0-9]{4})\s*</DestinationID>",
+            )
+            .expect("valid flight plan regex"),
+            simbrief_origin: section_icao("origin"),
+            simbrief_destination: section_icao("destination"),
+            simbrief_alternate: section_icao("alternate"),
+        }
+    })
+}
+
+fn first_capture(content: &str, regex: &Regex) -> Option<String> {
+    regex
+        .captures(content)?
+        .get(1)
+        .map(|icao| icao.as_str().trim().to_uppercase())
+}
+
+/// Read the airports from an MSFS .pln or a SimBrief XML flight plan
+pub fn parse_flight_plan(content: &str) -> Result<FlightPlanAirports, String> {
+    let patterns = flight_plan_patterns();
+
+    // MSFS: <DepartureID>EDDM</DepartureID> ... <DestinationID>EGLL</DestinationID>
+    let msfs = FlightPlanAirports {
+        departure: first_capture(content, &patterns.msfs_departure),
+        arrival: first_capture(content, &patterns.msfs_destination),
+        alternates: Vec::new(),
+    };
+    if msfs.departure.is_some() || msfs.arrival.is_some() {
+        return Ok(msfs);
+    }
+
+    // SimBrief: one <alternate> per alternate
+    let alternates = patterns
+        .simbrief_alternate
+        .captures_iter(content)
+        .filter_map(|cap| cap.get(1))
+        .map(|icao| icao.as_str().to_uppercase())
+        .collect();
+
+    let simbrief = FlightPlanAirports {
+        departure: first_capture(content, &patterns.simbrief_origin),
+        arrival: first_capture(content, &patterns.simbrief_destination),
+        alternates,
+    };
+    if simbrief.departure.is_none() && simbrief.arrival.is_none() {
+        return Err("No departure or arrival airport found in the flight plan".to_string());
+    }
+
+    Ok(simbrief)
+}
+
+/// The ICAOs of a route request, upper case and without duplicates
+pub fn route_icaos(request: &RouteRequest) -> Result<Vec<String>, String> {
+    let mut airports = FlightPlanAirports {
+        departure: request.departure.clone(),
+        arrival: request.arrival.clone(),
+        alternates: request.alternates.clone(),
+    };
+
+    // Airports given explicitly win over the ones in the flight plan
+    if let Some(flight_plan_path) = &request.flight_plan_path {
+        let content = fs::read_to_string(flight_plan_path)
+            .map_err(|e| format!("Failed to read flight plan: {}", e))?;
+        let plan = parse_flight_plan(&content)?;
+
+        airports.departure = airports.departure.or(plan.departure);
+        airports.arrival = airports.arrival.or(plan.arrival);
+        if airports.alternates.is_empty() {
+            airports.alternates = plan.alternates;
+        }
+    }
+
+    let mut icaos: Vec<String> = Vec::new();
+    for icao in airports
+        .departure
+        .into_iter()
+        .chain(airports.arrival)
+        .chain(airports.alternates)
+    {
+        let icao = icao.trim().to_uppercase();
+        if !icao.is_empty() && !icaos.contains(&icao) {
+            icaos.push(icao);
+        }
+    }
+
+    if icaos.is_empty() {
+        return Err("No airports given for the route".to_string());
+    }
+
+    Ok(icaos)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read directory: {}", e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+// The folder to activate among an airport's profile folders: the one already active,
+// otherwise the one with the most recently modified files. Ties go to the last folder by
+// path, which is the highest version for folders named like "1.0" and "2.0".
+fn pick_folder(folders: &[(PathBuf, Vec<PathBuf>)], active_sources: &[String]) -> usize {
+    let is_active = |files: &[PathBuf]| {
+        files
+            .iter()
+            .any(|file| active_sources.contains(&file.to_string_lossy().to_string()))
+    };
+    if let Some(index) = folders.iter().position(|(_, files)| is_active(files)) {
+        return index;
+    }
+
+    let newest = |files: &[PathBuf]| -> Option<SystemTime> {
+        files
+            .iter()
+            .filter_map(|file| fs::metadata(file).and_then(|m| m.modified()).ok())
+            .max()
+    };
+
+    (0..folders.len())
+        .max_by_key(|&index| (newest(&folders[index].1), &folders[index].0))
+        .unwrap_or(0)
+}
+
+/// Find the profile for each airport on the route. Only profile files are used, and only
+/// from one folder per airport, picked by pick_folder. `active_sources` are the sources
+/// linked in the GSX folder right now.
+pub fn match_route(
+    library_root: &Path,
+    icaos: &[String],
+    active_sources: &[String],
+) -> Result<RouteMatch, String> {
+    let mut library_files = Vec::new();
+    if library_root.exists() {
+        collect_files(library_root, &mut library_files)?;
+    }
+    library_files.retain(|path| is_profile_file(path));
+    library_files.sort();
+
+    let mut route = RouteMatch::default();
+    for icao in icaos {
+        // Profile folders for this airport with their files, in path order
+        let mut folders: Vec<(PathBuf, Vec<PathBuf>)> = Vec::new();
+        for path in &library_files {
+            let info = ProfileFileInfo::from_path(path);
+            if info.icao.as_deref() != Some(icao.as_str()) {
+                continue;
+            }
+
+            let folder = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            match folders.iter_mut().find(|(existing, _)| *existing == folder) {
+                Some((_, files)) => files.push(path.clone()),
+                None => folders.push((folder, vec![path.clone()])),
+            }
+        }
+
+        if folders.is_empty() {
+            route.unmatched.push(icao.clone());
+            route.airports.push(RouteAirport {
+                icao: icao.clone(),
+                folder: None,
+                files: Vec::new(),
+                other_folders: Vec::new(),
+            });
+            continue;
+        }
+
+        let picked = pick_folder(&folders, active_sources);
+        let to_string = |path: &PathBuf| path.to_string_lossy().to_string();
+
+        route.airports.push(RouteAirport {
+            icao: icao.clone(),
+            folder: Some(to_string(&folders[picked].0)),
+            files: folders[picked].1.iter().map(to_string).collect(),
+            other_folders: folders
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| *index != picked)
+                .map(|(_, (folder, _))| to_string(folder))
+                .collect(),
+        });
+    }
+
+    Ok(route)
+}
+
+#[command]
+pub fn match_route_profiles(app: AppHandle, route: RouteRequest) -> Result<RouteMatch, String> {
+    let icaos = route_icaos(&route)?;
+    let active_sources = activation_history::active_files(&get_gsx_target_dir(&app)?)?;
+    match_route(&get_library_root(&app)?, &icaos, &active_sources)
+}
+
+/// Activate only the profiles for the airports of a flight
+#[command]
+pub async fn activate_route(
+    app: AppHandle,
+    route: RouteRequest,
+    options: Option<ActivationOptions>,
+) -> Result<RouteActivationReport, ActivationError> {
+    let icaos = route_icaos(&route).map_err(|message| ActivationError::Route { message })?;
+    let library_root = get_library_root(&app).map_err(ActivationError::settings)?;
+    let target_dir = get_gsx_target_dir(&app).map_err(ActivationError::target_dir)?;
+    let active_sources =
+        activation_history::active_files(&target_dir).map_err(ActivationError::target_dir)?;
+    let route_match = match_route(&library_root, &icaos, &active_sources)
+        .map_err(|message| ActivationError::Route { message })?;
+
+    // With keep_other_links, the active links stay except the profiles for airports on the
+    // route, which are replaced by the route's own files
+    let mut selected_files: Vec<String> = Vec::new();
+    if route.keep_other_links {
+        selected_files = active_sources
+            .iter()
+            .filter(|source| {
+                let info = ProfileFileInfo::from_path(Path::new(source));
+                !info.icao.is_some_and(|icao| icaos.contains(&icao))
+            })
+            .cloned()
+            .collect();
+    }
+
+    let route_files: Vec<String> = route_match
+        .airports
+        .iter()
+        .flat_map(|airport| airport.files.clone())
+        .collect();
+    if route_files.is_empty() {
+        return Err(ActivationError::Route {
+            message: format!("No profiles found for {}", icaos.join(", ")),
+        });
+    }
+    selected_files.extend(route_files);
+
+    println!(
+        "Activating route {} ({} files, {} airports without a profile)",
+        icaos.join(" - "),
+        selected_files.len(),
+        route_match.unmatched.len()
+    );
+
+    let activation =
+        create_profile_symlink::activate_profiles(app, selected_files, options).await?;
+
+    Ok(RouteActivationReport {
+        route: route_match,
+        activation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "profile").unwrap();
+    }
+
+    fn to_string(path: PathBuf) -> String {
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn parses_msfs_and_simbrief_flight_plans() {
+        let pln = "<FlightPlan.FlightPlan><DepartureID>eddm</DepartureID>\
+                   <DestinationID> EGLL </DestinationID></FlightPlan.FlightPlan>";
+        let plan = parse_flight_plan(pln).unwrap();
+        assert_eq!(plan.departure.as_deref(), Some("EDDM"));
+        assert_eq!(plan.arrival.as_deref(), Some("EGLL"));
+
+        let ofp = "<OFP><origin><icao_code>KJFK</icao_code></origin>\
+                   <destination><icao_code>EGLL</icao_code></destination>\
+                   <alternate><icao_code>EGKK</icao_code></alternate>\
+                   <alternate><icao_code>EGSS</icao_code></alternate></OFP>";
+        let plan = parse_flight_plan(ofp).unwrap();
+        assert_eq!(plan.departure.as_deref(), Some("KJFK"));
+        assert_eq!(plan.alternates, vec!["EGKK", "EGSS"]);
+
+        assert!(parse_flight_plan("<OFP></OFP>").is_err());
+    }
+
+    #[test]
+    fn picks_one_profile_folder_per_airport() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("gsx-profiles");
+        let eddm = library.join("Europe/Germany/eddm");
+        write(&eddm.join("aerosoft/1.0/eddm-aerosoft.ini"));
+        write(&eddm.join("aerosoft/2.0/eddm-aerosoft.ini"));
+        write(&eddm.join("aerosoft/2.0/eddm-aerosoft.py"));
+        write(&eddm.join("aerosoft/2.0/readme.txt"));
+        write(&eddm.join("aerosoft/2.0/preview.png"));
+
+        let route = match_route(&library, &["EDDM".into(), "EGLL".into()], &[]).unwrap();
+
+        // Same modification time, so the last version folder wins
+        let eddm_route = &route.airports[0];
+        assert_eq!(
+            eddm_route.folder,
+            Some(to_string(eddm.join("aerosoft/2.0")))
+        );
+        assert_eq!(
+            eddm_route.files,
+            vec![
+                to_string(eddm.join("aerosoft/2.0/eddm-aerosoft.ini")),
+                to_string(eddm.join("aerosoft/2.0/eddm-aerosoft.py")),
+            ]
+        );
+        assert_eq!(
+            eddm_route.other_folders,
+            vec![to_string(eddm.join("aerosoft/1.0"))]
+        );
+        assert_eq!(route.unmatched, vec!["EGLL"]);
+        assert!(route.airports[1].folder.is_none());
+    }
+
+    #[test]
+    fn keeps_the_active_profile_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("gsx-profiles");
+        let eddm = library.join("Europe/Germany/eddm");
+        write(&eddm.join("aerosoft/eddm-aerosoft.ini"));
+        write(&eddm.join("justsim/eddm-justsim.ini"));
+        let active = vec![to_string(eddm.join("aerosoft/eddm-aerosoft.ini"))];
+
+        let route = match_route(&library, &["EDDM".into()], &active).unwrap();
+
+        assert_eq!(route.airports[0].files, active);
+        assert_eq!(
+            route.airports[0].other_folders,
+            vec![to_string(eddm.join("justsim"))]
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{command, AppHandle, Manager};
+
+use crate::airport_community_scanner::ScanRoot;
+use crate::link_strategy::LinkStrategy;
+use crate::profile_backups::{BackupFormat, RetentionPolicy};
+use crate::profile_conflicts::ConflictPolicy;
+use crate::sim_guard::SimGuardSettings;
+
+const SETTINGS_FILE_NAME: &str = "backend-settings.json";
+
+// Settings used by the Rust backend. Missing fields fall back to their defaults so
+// older settings files keep loading when new options are added.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct BackendSettings {
+    pub backup_retention: RetentionPolicy,
+    pub backup_format: BackupFormat,
+    pub backup_location: Option<String>, // Where zip backups go; None uses the app data folder
+    pub link_strategy: LinkStrategy,     // Auto picks the best strategy per file
+    pub gsx_target_dir: Option<String>,  // None auto-detects the GSX profile folder
+    pub conflict_policy: ConflictPolicy,
+    pub sim_guard: SimGuardSettings,
+    pub scan_roots: Vec<ScanRoot>, // Scanned for airports in addition to the MSFS folders
+    pub read_official_scenery: bool, // Read the BGLs of Official and StreamedPackages too
+}
+
+/// The app data folder, where the library, settings and the other app files live
+pub fn app_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+}
+
+// Settings live next to the gsx-profiles library in the app data folder
+fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app)?.join(SETTINGS_FILE_NAME))
+}
+
+pub fn load_settings(app: &AppHandle) -> Result<BackendSettings, String> {
+    let settings_path = get_settings_path(app)?;
+
+    if !settings_path.exists() {
+        return Ok(BackendSettings::default());
+    }
+
+    let content = fs::read_to_string(&settings_path)
+        .map_err(|e| format!("Failed to read settings file: {}", e))?;
+
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse settings file: {}", e))
+}
+
+pub fn save_settings(app: &AppHandle, settings: &BackendSettings) -> Result<(), String> {
+    let settings_path = get_settings_path(app)?;
+
+    if let Some(parent) = settings_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    fs::write(&settings_path, content).map_err(|e| format!("Failed to write settings file: {}", e))
+}
+
+#[command]
+pub fn get_backend_settings(app: AppHandle) -> Result<BackendSettings, String> {
+    load_settings(&app)
+}
+
+#[command]
+pub fn update_backend_settings(app: AppHandle, settings: BackendSettings) -> Result<(), String> {
+    save_settings(&app, &settings)
+}
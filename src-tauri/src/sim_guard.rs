@@ -0,0 +1,362 @@
+use serde::{Deserialize, Serialize};
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use crate::activation_progress::{ActivationProgress, ProgressReporter};
+use crate::activation_report::ActivationError;
+
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+// How often cancellation is checked while waiting for the simulator to exit
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+// ps on Linux cuts process names to 15 characters, so "Couatl64_MSFS.exe" running under
+// Wine or Proton is listed as "Couatl64_MSFS.e"
+const TRUNCATED_NAME_LEN: usize = 15;
+
+/// What an activation does while the simulator or GSX is running
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SimRunningPolicy {
+    // Activate anyway and list the running processes in the report
+    #[default]
+    Warn,
+    Refuse,
+    // Wait until they have all exited, then activate
+    Queue,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SimGuardSettings {
+    pub policy: SimRunningPolicy,
+    pub process_names: Vec<String>, // Matched ignoring case, with or without ".exe"
+    pub poll_interval_secs: u64,    // How often Queue checks whether they have exited
+}
+
+impl Default for SimGuardSettings {
+    fn default() -> Self {
+        SimGuardSettings {
+            policy: SimRunningPolicy::Warn,
+            process_names: vec![
+                "FlightSimulator.exe".to_string(),
+                "FlightSimulator2024.exe".to_string(),
+                "Couatl64_MSFS.exe".to_string(),
+                "Couatl64_MSFS2024.exe".to_string(),
+            ],
+            poll_interval_secs: 5,
+        }
+    }
+}
+
+/// Lists the names of the running processes. The tests below pass their own.
+pub trait ProcessLister: Send + Sync {
+    fn running_processes(&self) -> Result<Vec<String>, String>;
+}
+
+/// Lists processes with tasklist on Windows and ps elsewhere
+pub struct SystemProcessLister;
+
+impl ProcessLister for SystemProcessLister {
+    #[cfg(target_os = "windows")]
+    fn running_processes(&self) -> Result<Vec<String>, String> {
+        let output = Command::new("tasklist")
+            .args(["/FO", "CSV", "/NH"])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .map_err(|e| format!("Failed to list processes: {}", e))?;
+
+        // Each line looks like "FlightSimulator.exe","1234","Console","1","2,345,678 K"
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split("\",\"").next())
+            .map(|name| name.trim_matches('"').to_string())
+            .filter(|name| !name.is_empty())
+            .collect())
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn running_processes(&self) -> Result<Vec<String>, String> {
+        let output = Command::new("ps")
+            .args(["-A", "-o", "comm="])
+            .output()
+            .map_err(|e| format!("Failed to list processes: {}", e))?;
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.trim().rsplit('/').next().unwrap_or("").to_string())
+            .filter(|name| !name.is_empty())
+            .collect())
+    }
+}
+
+// "FlightSimulator.exe" and "flightsimulator" are the same process
+fn process_key(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    name.strip_suffix(".exe").unwrap_or(&name).to_string()
+}
+
+// Whether a listed process is the watched one, also when ps cut its name short
+fn is_watched(watched: &str, listed: &str) -> bool {
+    if process_key(watched) == process_key(listed) {
+        return true;
+    }
+
+    let watched = watched.trim().to_lowercase();
+    let listed = listed.trim().to_lowercase();
+    listed.chars().count() == TRUNCATED_NAME_LEN && watched.starts_with(&listed)
+}
+
+/// Checks for the simulator and GSX before an activation
+pub struct SimGuard {
+    lister: Box<dyn ProcessLister>,
+    settings: SimGuardSettings,
+}
+
+impl SimGuard {
+    pub fn new(lister: Box<dyn ProcessLister>, settings: SimGuardSettings) -> SimGuard {
+        SimGuard { lister, settings }
+    }
+
+    pub fn system(settings: SimGuardSettings) -> SimGuard {
+        SimGuard::new(Box::new(SystemProcessLister), settings)
+    }
+
+    /// The configured processes that are running right now, as the lister names them
+    pub fn running(&self) -> Result<Vec<String>, String> {
+        let mut running: Vec<String> = self
+            .lister
+            .running_processes()?
+            .into_iter()
+            .filter(|name| {
+                self.settings
+                    .process_names
+                    .iter()
+                    .any(|watched| is_watched(watched, name))
+            })
+            .collect();
+        running.sort();
+        running.dedup();
+
+        Ok(running)
+    }
+
+    /// Apply the policy, the one from the settings unless `policy` overrides it. Returns the
+    /// processes that are still running when the activation may go ahead, which is only ever
+    /// non-empty with Warn.
+    pub fn check(
+        &self,
+        policy: Option<SimRunningPolicy>,
+        progress: &ProgressReporter,
+    ) -> Result<Vec<String>, ActivationError> {
+        let policy = policy.unwrap_or(self.settings.policy);
+        let running = match self.running() {
+            Ok(running) => running,
+            // Only Warn may go ahead without knowing
+            Err(e) if policy == SimRunningPolicy::Warn => {
+                println!("Failed to check for the simulator: {}", e);
+                Vec::new()
+            }
+            Err(e) => return Err(Self::check_failed(e)),
+        };
+
+        if running.is_empty() {
+            return Ok(running);
+        }
+
+        match policy {
+            SimRunningPolicy::Warn => {
+                println!(
+                    "Activating while the simulator is running: {}",
+                    running.join(", ")
+                );
+                Ok(running)
+            }
+            SimRunningPolicy::Refuse => Err(ActivationError::SimulatorRunning {
+                message: format!(
+                    "Close the simulator before changing GSX profiles: {} running",
+                    running.join(", ")
+                ),
+                processes: running,
+            }),
+            SimRunningPolicy::Queue => self.wait_until_closed(running, progress),
+        }
+    }
+
+    fn check_failed(message: String) -> ActivationError {
+        ActivationError::SimulatorRunning {
+            message: format!(
+                "Could not check whether the simulator is running: {}",
+                message
+            ),
+            processes: Vec::new(),
+        }
+    }
+
+    // Poll until none of the processes run anymore; cancel_activation stops the wait
+    fn wait_until_closed(
+        &self,
+        mut running: Vec<String>,
+        progress: &ProgressReporter,
+    ) -> Result<Vec<String>, ActivationError> {
+        let poll_interval = Duration::from_secs(self.settings.poll_interval_secs.max(1));
+
+        while !running.is_empty() {
+            println!("Waiting for the simulator to exit: {}", running.join(", "));
+            progress.report(ActivationProgress::WaitingForSimulator {
+                processes: running.clone(),
+            });
+
+            let mut waited = Duration::ZERO;
+            while waited < poll_interval {
+                if progress.is_cancelled() {
+                    return Err(ActivationError::Cancelled);
+                }
+                let step = CANCEL_CHECK_INTERVAL.min(poll_interval - waited);
+                thread::sleep(step);
+                waited += step;
+            }
+
+            running = self.running().map_err(Self::check_failed)?;
+        }
+
+        Ok(running)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{Arc, Mutex};
+
+    // Returns the given process lists one check after another, repeating the last one
+    struct FakeLister(Mutex<Vec<Result<Vec<String>, String>>>);
+
+    impl FakeLister {
+        fn new(checks: Vec<Result<Vec<&str>, &str>>) -> Box<FakeLister> {
+            let checks = checks
+                .into_iter()
+                .map(|check| {
+                    check
+                        .map(|names| names.into_iter().map(String::from).collect())
+                        .map_err(String::from)
+                })
+                .collect();
+            Box::new(FakeLister(Mutex::new(checks)))
+        }
+    }
+
+    impl ProcessLister for FakeLister {
+        fn running_processes(&self) -> Result<Vec<String>, String> {
+            let mut checks = self.0.lock().unwrap();
+            if checks.len() > 1 {
+                checks.remove(0)
+            } else {
+                checks[0].clone()
+            }
+        }
+    }
+
+    fn guard(checks: Vec<Result<Vec<&str>, &str>>, policy: SimRunningPolicy) -> SimGuard {
+        SimGuard::new(
+            FakeLister::new(checks),
+            SimGuardSettings {
+                policy,
+                poll_interval_secs: 1,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn guard_failing(policy: SimRunningPolicy) -> SimGuard {
+        guard(vec![Err("ps not found")], policy)
+    }
+
+    #[test]
+    fn matches_names_ignoring_case_extension_and_truncation() {
+        let guard = guard(
+            vec![Ok(vec![
+                "flightsimulator.EXE",
+                "Couatl64_MSFS.e",
+                "explorer.exe",
+                "Couatl64",
+            ])],
+            SimRunningPolicy::Warn,
+        );
+
+        assert_eq!(
+            guard.running().unwrap(),
+            vec!["Couatl64_MSFS.e", "flightsimulator.EXE"]
+        );
+    }
+
+    #[test]
+    fn warn_goes_ahead_and_reports_running_processes() {
+        let guard = guard(
+            vec![Ok(vec!["FlightSimulator.exe"])],
+            SimRunningPolicy::Warn,
+        );
+        let running = guard.check(None, &ProgressReporter::default()).unwrap();
+        assert_eq!(running, vec!["FlightSimulator.exe"]);
+
+        // Not knowing what runs only stops the stricter policies
+        let guard = guard_failing(SimRunningPolicy::Warn);
+        assert!(guard
+            .check(None, &ProgressReporter::default())
+            .unwrap()
+            .is_empty());
+        let refused = guard.check(Some(SimRunningPolicy::Refuse), &ProgressReporter::default());
+        assert!(matches!(
+            refused,
+            Err(ActivationError::SimulatorRunning { .. })
+        ));
+    }
+
+    #[test]
+    fn refuse_fails_while_running() {
+        let guard = guard(
+            vec![Ok(vec!["Couatl64_MSFS.exe"])],
+            SimRunningPolicy::Refuse,
+        );
+
+        match guard.check(None, &ProgressReporter::default()) {
+            Err(ActivationError::SimulatorRunning { processes, .. }) => {
+                assert_eq!(processes, vec!["Couatl64_MSFS.exe"])
+            }
+            other => panic!("expected SimulatorRunning, got {:?}", other),
+        }
+        assert!(guard
+            .check(Some(SimRunningPolicy::Warn), &ProgressReporter::default())
+            .is_ok());
+    }
+
+    #[test]
+    fn queue_waits_until_closed() {
+        let guard = guard(
+            vec![Ok(vec!["FlightSimulator2024.exe"]), Ok(vec![])],
+            SimRunningPolicy::Queue,
+        );
+
+        let running = guard.check(None, &ProgressReporter::default()).unwrap();
+        assert!(running.is_empty());
+    }
+
+    #[test]
+    fn queue_stops_when_cancelled() {
+        let guard = guard(
+            vec![Ok(vec!["FlightSimulator.exe"])],
+            SimRunningPolicy::Queue,
+        );
+        let progress = ProgressReporter::with_cancel(Arc::new(AtomicBool::new(true)));
+
+        assert!(matches!(
+            guard.check(None, &progress),
+            Err(ActivationError::Cancelled)
+        ));
+    }
+}
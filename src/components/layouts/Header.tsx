@@ -27,6 +27,7 @@ import UserSettings from "@/features/user-settings/components/user-settings";
 import AirportProfileMatcher from "@/features/airport-profile-matcher/components/airportProfileMatcher";
 import UpdateChecker from "@/features/update-checker/components/UpdateChecker";
 import SimBriefRoute from "@/features/simbrief-checker/SimBriefRoute";
+import { type ActivationReport, isActivationError } from "@/types/activation";
 
 function Header() {
   // State to control the visibility of the profile uploader modal
@@ -55,14 +56,32 @@ function Header() {
       const filePaths = syncedProfiles.flatMap(profile => profile.filePaths);
 
       // Call the Rust command to activate profiles - Use camelCase for parameter names
-      const result = await invoke<string>("activate_profiles", {
-        selectedFiles: filePaths // Changed from selected_files to selectedFiles
+      const report = await invoke<ActivationReport>("activate_profiles", {
+        selectedFiles: filePaths, // Changed from selected_files to selectedFiles
+        options: { profile_ids: syncedProfiles.map(profile => profile.id) }
       });
 
       await markProfilesAsApplied(syncedProfiles.map(p => p.id));
 
-      toast.success(result);
+      const { added, retargeted, removed, unchanged } = report.delta;
+      if (report.linked.length > 0 || unchanged.length > 0) {
+        toast.success("GSX profiles activated successfully! Your simulator is ready to use these profiles.", {
+          description: `${added.length} added, ${retargeted.length} updated, ${removed.length} removed, ${unchanged.length} unchanged` +
+            (report.missing_sources.length > 0 ? `, ${report.missing_sources.length} missing` : "") +
+            (report.skipped.length > 0 ? `, ${report.skipped.length} skipped` : ""),
+        });
+      } else {
+        toast.warning("No profiles were activated. Please check your selected profiles.");
+      }
     } catch (error) {
+      if (isActivationError(error)) {
+        if (error.kind === "cancelled") {
+          toast.info("Operation cancelled by user.");
+        } else {
+          toast.error(`Failed to apply profiles: ${error.message}`);
+        }
+        return;
+      }
       toast.error(`Failed to apply profiles: ${error instanceof Error ? error.message : String(error)}`);
     } finally {
       setIsApplying(false);
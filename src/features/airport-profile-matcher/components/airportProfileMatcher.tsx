@@ -13,10 +13,15 @@ interface AirportInfo {
     icao: string;
     title: string;
     path: string;
+    link_path: string | null;  // Where it was found, when that was through a link
     folder_type: string;
+    label: string;
     developer: string;
     fsversion: string;
     name: string;
+    package_version: string | null;  // From the package's manifest.json
+    minimum_game_version: string | null;
+    package_icaos: string[];  // Every airport of the same package
 }
 
 interface AirportMatchStatus extends AirportInfo {
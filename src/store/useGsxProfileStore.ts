@@ -1,8 +1,10 @@
 import { create } from 'zustand';
 import { persist, createJSONStorage } from 'zustand/middleware';
 import { GSXProfile } from '@/types/gsx-profile';
+import { AdoptionMode, AdoptionRecord } from '@/types/adoption';
 import { initializeDb, addProfile, updateProfile, deleteProfile, updateProfileStatus, getAllProfiles, closeDb, markProfilesAsApplied } from '@/lib/db';
 import { deleteProfileFiles } from '@/lib/fileSystem';
+import { invoke } from '@tauri-apps/api/core';
 
 interface ProfileState {
     profiles: GSXProfile[];
@@ -12,6 +14,7 @@ interface ProfileState {
     // Actions
     initializeStore: () => Promise<void>;
     addProfile: (profileData: GSXProfile) => Promise<GSXProfile>;
+    adoptGsxProfiles: (mode?: AdoptionMode) => Promise<AdoptionRecord>;
     removeProfile: (id: string) => Promise<void>;
     syncProfile: (id: string) => Promise<void>;
     unsyncProfile: (id: string) => Promise<void>;
@@ -81,6 +84,44 @@ export const useProfileStore = create<ProfileState>()(
                 }
             },
 
+            // Adopt the hand-placed profiles in the GSX folder and list them as selected profiles,
+            // otherwise the next activation would remove their links
+            adoptGsxProfiles: async (mode?: AdoptionMode) => {
+                set({ isLoading: true, error: null });
+                try {
+                    const record = await invoke<AdoptionRecord>('adopt_gsx_profiles', { mode });
+
+                    const adoptedProfiles: GSXProfile[] = [];
+                    for (const group of record.groups) {
+                        const now = new Date();
+                        const profile = await addProfile({
+                            id: crypto.randomUUID(),
+                            status: true,
+                            continent: 'Unknown',
+                            country: 'unknown',
+                            airportIcaoCode: group.icao,
+                            airportDeveloper: group.developer || undefined,
+                            filePaths: group.files.map(file => file.library),
+                            createdAt: now,
+                            updatedAt: now
+                        });
+                        adoptedProfiles.push(profile);
+                    }
+
+                    set(state => ({
+                        profiles: [...state.profiles, ...adoptedProfiles],
+                        isLoading: false
+                    }));
+                    return record;
+                } catch (error) {
+                    set({
+                        isLoading: false,
+                        error: error instanceof Error ? error.message : 'Failed to adopt profiles'
+                    });
+                    throw error;
+                }
+            },
+
             removeProfile: async (id: string) => {
                 set({ isLoading: true, error: null });
                 try {
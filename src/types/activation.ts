@@ -0,0 +1,90 @@
+// Mirrors ActivationReport / ActivationError in src-tauri/src/activation_report.rs
+
+export type UnmanagedPolicy = 'preserve' | 'replace' | 'adopt';
+
+export type ConflictPolicy =
+    | { type: 'fail' }
+    | { type: 'prefer_newest' }
+    | { type: 'prefer_developer'; developer: string }
+    | { type: 'keep_first' };
+
+export type OverwritePolicy = 'ask' | 'always' | 'never';
+
+export type SimRunningPolicy = 'warn' | 'refuse' | 'queue';
+
+// Options for activate_profiles; anything left out falls back to the backend settings
+export interface ActivationOptions {
+    conflict_policy?: ConflictPolicy;
+    unmanaged_policy?: UnmanagedPolicy;
+    overwrite?: OverwritePolicy;
+    backup?: boolean;
+    profile_ids?: string[]; // Recorded in zip backups
+    sim_running_policy?: SimRunningPolicy;
+}
+
+export type LinkStrategy = 'auto' | 'windows_symlink' | 'unix_symlink' | 'hardlink' | 'copy';
+
+export interface LinkedFile {
+    source: string;
+    target: string;
+    strategy: LinkStrategy;
+}
+
+export interface SkippedSource {
+    source: string;
+    reason: string;
+}
+
+export interface ProfileConflict {
+    kind: 'file_name' | 'icao';
+    key: string;
+    files: string[];
+    kept?: string | null;
+}
+
+export interface ActivationDelta {
+    added: string[];
+    retargeted: string[];
+    removed: string[];
+    unchanged: string[];
+    preserved: string[];
+}
+
+export interface ActivationReport {
+    target_dir: string;
+    linked: LinkedFile[];
+    skipped: SkippedSource[];
+    missing_sources: string[];
+    removed: string[];
+    delta: ActivationDelta;
+    conflicts: ProfileConflict[];
+    backup?: string | null;
+    sim_running: string[]; // Simulator processes running during the activation (warn policy)
+    duration_ms: number;
+}
+
+export type ActivationError =
+    | { kind: 'cancelled' }
+    | { kind: 'conflict'; message: string; conflicts: ProfileConflict[] }
+    | { kind: 'unmanaged_files'; message: string; files: string[] }
+    | { kind: 'simulator_running'; message: string; processes: string[] }
+    | { kind: 'target_dir' | 'settings' | 'loadout' | 'route' | 'history' | 'dialog' | 'backup' | 'link'; message: string };
+
+export function isActivationError(error: unknown): error is ActivationError {
+    return typeof error === 'object' && error !== null && 'kind' in error;
+}
+
+// Emitted as PROGRESS_EVENT while an activation runs, see activation_progress.rs
+export const PROGRESS_EVENT = 'activation-progress';
+
+export type ActivationProgress =
+    | { stage: 'started'; total: number }
+    | { stage: 'waiting_for_simulator'; processes: string[] }
+    | { stage: 'backup_copied'; current: number; total: number; file: string }
+    | { stage: 'linked'; current: number; total: number; source: string }
+    | { stage: 'finished'; duration_ms: number }
+    | { stage: 'cancelled' }
+    | { stage: 'failed'; message: string };
+
+// The PROGRESS_EVENT payload; pass activation_id to cancel_activation to stop that activation
+export type ActivationProgressEvent = ActivationProgress & { activation_id: string };
@@ -0,0 +1,39 @@
+// Mirrors the types in src-tauri/src/profile_adoption.rs
+
+import type { SkippedSource } from './activation';
+
+export type AdoptionMode = 'move' | 'copy';
+
+export interface AdoptionGroup {
+    icao: string;
+    developer?: string | null;
+    library_dir: string;
+    files: string[];
+}
+
+export interface AdoptionScan {
+    target_dir: string;
+    groups: AdoptionGroup[];
+    skipped: SkippedSource[];
+}
+
+export interface AdoptedFile {
+    original: string;
+    library: string;
+}
+
+export interface AdoptedGroup {
+    icao: string;
+    developer?: string | null;
+    library_dir: string;
+    files: AdoptedFile[];
+}
+
+export interface AdoptionRecord {
+    id: string;
+    timestamp: number;
+    target_dir: string;
+    mode: AdoptionMode;
+    backup_dir?: string | null;
+    groups: AdoptedGroup[];
+}
@@ -0,0 +1,48 @@
+// Mirrors the types in src-tauri/src/profile_backups.rs and src-tauri/src/backup_archive.rs
+
+export type BackupFormat = 'folder' | 'zip';
+
+export interface RetentionPolicy {
+    keep_last?: number | null;
+    max_age_days?: number | null;
+}
+
+export interface BackupInfo {
+    id: string;
+    path: string;
+    format: BackupFormat;
+    timestamp: number;
+    file_count: number;
+    size_bytes: number; // Compressed size for zip backups
+}
+
+export interface BackupFile {
+    name: string;
+    size_bytes: number;
+}
+
+export interface ArchivedFile {
+    name: string;
+    original_path: string;
+    size_bytes: number;
+    sha256: string;
+}
+
+export interface ManagedEntry {
+    name: string;
+    source: string;
+}
+
+export interface BackupArchiveManifest {
+    created_at: number;
+    target_dir: string;
+    files: ArchivedFile[];
+    active_profile_ids: string[];
+    active_links: ManagedEntry[];
+}
+
+export interface BackupDetails {
+    info: BackupInfo;
+    files: BackupFile[];
+    manifest: BackupArchiveManifest | null; // Only zip backups have one
+}
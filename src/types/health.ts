@@ -0,0 +1,28 @@
+// Mirrors the types in src-tauri/src/activation_health.rs
+
+// Event emitted on startup when the GSX folder has drifted from the last activation
+export const HEALTH_EVENT = 'gsx-folder-health';
+
+export type HealthIssueKind = 'dangling' | 'missing' | 'changed' | 'foreign';
+
+export interface HealthIssue {
+    kind: HealthIssueKind;
+    path: string;
+    expected_source?: string | null;
+    actual_source?: string | null;
+}
+
+export interface HealthReport {
+    target_dir: string;
+    checked_at: number;
+    has_manifest: boolean;
+    checked: number;
+    issues: HealthIssue[];
+}
+
+export interface RepairReport {
+    backup?: string | null;
+    relinked: string[];
+    removed: string[];
+    health: HealthReport;
+}
@@ -0,0 +1,23 @@
+// Mirrors the types in src-tauri/src/activation_history.rs
+
+import type { ActivationDelta } from './activation';
+
+export interface ActivationRecord {
+    id: string;
+    timestamp: number;
+    target_dir: string;
+    files: string[];
+    linked: string[];
+    removed: string[];
+    backup_id: string | null;
+    previous_files: string[];
+    undone: boolean;
+}
+
+export interface UndoReport {
+    undone: string[];
+    files: string[];
+    missing_sources: string[];
+    delta: ActivationDelta;
+    backup: string | null;
+}
@@ -0,0 +1,16 @@
+// Mirrors the types in src-tauri/src/loadouts.rs
+
+export interface Loadout {
+    name: string;
+    files: string[];
+    created_at: number;
+    updated_at: number;
+}
+
+export interface LoadoutDiff {
+    from: string;
+    to: string;
+    added: string[];
+    removed: string[];
+    unchanged: string[];
+}
@@ -0,0 +1,28 @@
+// Mirrors the types in src-tauri/src/route_activation.rs
+
+import type { ActivationReport } from './activation';
+
+export interface RouteRequest {
+    departure?: string | null;
+    arrival?: string | null;
+    alternates?: string[];
+    flight_plan_path?: string | null;
+    keep_other_links?: boolean;
+}
+
+export interface RouteAirport {
+    icao: string;
+    folder: string | null;
+    files: string[];
+    other_folders: string[];
+}
+
+export interface RouteMatch {
+    airports: RouteAirport[];
+    unmatched: string[];
+}
+
+export interface RouteActivationReport {
+    route: RouteMatch;
+    activation: ActivationReport;
+}
@@ -0,0 +1,21 @@
+// Mirrors ScanRoot and AirportPackage in src-tauri/src/airport_community_scanner.rs
+
+export interface ScanRoot {
+    path: string;
+    label: string;
+    folder_type: string;
+}
+
+export interface AirportPackage {
+    title: string;
+    path: string;
+    link_path: string | null;
+    folder_type: string;
+    label: string;
+    icaos: string[];
+    developer: string;
+    fsversion: string;
+    name: string;
+    package_version: string | null;
+    minimum_game_version: string | null;
+}